@@ -0,0 +1,18 @@
+#!/bin/sh
+# Replaces the bundled copy of gl.xml with the one from the given revision
+# (branch, tag or commit) of the Khronos OpenGL-Registry repository. The
+# revision is resolved to a commit hash, which is recorded in REVISION.
+set -e
+cd "$(dirname "$0")"
+REPO=https://github.com/KhronosGroup/OpenGL-Registry
+REV=${1:-main}
+if ! echo "$REV" | grep -Eq '^[0-9a-f]{40}$'; then
+    REV=$(git ls-remote "$REPO" "$REV" | head -n 1 | cut -f 1)
+    if [ -z "$REV" ]; then
+        echo "$1: no such revision" >&2
+        exit 1
+    fi
+fi
+curl -fsSL "https://raw.githubusercontent.com/KhronosGroup/OpenGL-Registry/$REV/xml/gl.xml" | gzip -9n > gl.xml.gz.new
+mv gl.xml.gz.new gl.xml.gz
+echo "$REV" > REVISION
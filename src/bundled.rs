@@ -0,0 +1,18 @@
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+/// `gl.xml`, gzipped. Run `registry/update.sh` to change it.
+static REGISTRY: &[u8] = include_bytes!("../registry/gl.xml.gz");
+static REVISION: &str = include_str!("../registry/REVISION");
+
+/// The OpenGL-Registry commit the bundled `gl.xml` came from.
+pub fn revision() -> &'static str {
+    REVISION.trim()
+}
+
+pub fn read_registry() -> Vec<u8> {
+    let mut ret = Vec::new();
+    GzDecoder::new(REGISTRY).read_to_end(&mut ret).unwrap();
+    ret
+}
@@ -0,0 +1,194 @@
+use std::{
+    env, fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Starts every cache file. Change the number whenever the format of what's
+/// cached changes.
+const MAGIC: &[u8] = b"rglgen registry cache 2\n";
+
+/// Where to cache gathered registries if neither `--registry-cache` nor
+/// `--no-registry-cache` is given: in `OUT_DIR` when rglgen is run from a
+/// build script, and nowhere otherwise.
+pub fn default_registry_cache() -> Option<PathBuf> {
+    env::var_os("OUT_DIR").map(|x| PathBuf::from(x).join("rglgen-cache"))
+}
+
+/// Returns the header a cache file must start with to hold what was gathered
+/// from `source` with the options described by `key`: the magic, then a
+/// SHA-256 of the whole registry, then a SHA-256 of the rglgen version and
+/// `key`.
+fn make_header(source: &[u8], key: &str) -> Vec<u8> {
+    let mut ret = MAGIC.to_vec();
+    ret.extend_from_slice(&Sha256::digest(source));
+    ret.extend_from_slice(
+        &Sha256::new()
+            .chain_update(env!("CARGO_PKG_VERSION"))
+            .chain_update([0])
+            .chain_update(key)
+            .finalize(),
+    );
+    ret
+}
+
+/// Creates the cache directory if need be, and makes sure nobody else can
+/// put files in it, since what's in there ends up in generated code.
+#[cfg(unix)]
+fn prepare_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    if fs::metadata(dir)?.mode() & 0o022 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "other users can write to it",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn prepare_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+/// Makes sure a cache file was written by whoever owns the cache directory,
+/// and that nobody else could have changed it since.
+#[cfg(unix)]
+fn check_file(dir: &Path, file: &fs::File) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = file.metadata()?;
+    if metadata.uid() != fs::metadata(dir)?.uid()
+        || metadata.mode() & 0o022 != 0
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "cache file is not trustworthy",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_file(_dir: &Path, _file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+fn read_cache<T: DeserializeOwned>(
+    dir: &Path,
+    path: &Path,
+    header: &[u8],
+) -> io::Result<T> {
+    let file = fs::File::open(path)?;
+    check_file(dir, &file)?;
+    let mut input = BufReader::new(file);
+    let mut found = vec![0; header.len()];
+    input.read_exact(&mut found)?;
+    if found != header {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a cache of this registry",
+        ));
+    }
+    bincode::deserialize_from(input)
+        .map_err(|x| io::Error::new(io::ErrorKind::InvalidData, x))
+}
+
+#[cfg(unix)]
+fn create_file(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_file(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+}
+
+fn write_cache_file<T: Serialize>(
+    path: &Path,
+    header: &[u8],
+    gathered: &T,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(create_file(path)?);
+    out.write_all(header)?;
+    bincode::serialize_into(&mut out, gathered).map_err(io::Error::other)?;
+    out.flush()
+}
+
+fn write_cache<T: Serialize>(
+    path: &Path,
+    header: &[u8],
+    gathered: &T,
+) -> io::Result<()> {
+    // Several build scripts may be filling the cache at once, so make sure
+    // nobody can see a half-written file.
+    let temp = path.with_extension(format!("{}.tmp", process::id()));
+    let result = write_cache_file(&temp, header, gathered)
+        .and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Returns what `gather` gathers from the registry `source`, unless that has
+/// been cached in `cache_dir` before. `key` must describe every option that
+/// `gather` depends on. The cache is keyed on a SHA-256 of the registry and
+/// `key`, which is checked again on reading, so it never needs to be cleared
+/// by hand.
+pub fn load_cached<T, F>(
+    source: &[u8],
+    key: &str,
+    cache_dir: Option<&Path>,
+    gather: F,
+) -> T
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> T,
+{
+    let cache_dir = match cache_dir {
+        None => return gather(),
+        Some(cache_dir) => cache_dir,
+    };
+    if let Err(x) = prepare_dir(cache_dir) {
+        eprintln!(
+            "warning: not using the registry cache {}: {}",
+            cache_dir.display(),
+            x
+        );
+        return gather();
+    }
+    let header = make_header(source, key);
+    let name: String = Sha256::digest(&header[MAGIC.len()..])[..16]
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect();
+    let path = cache_dir.join(format!("gl-{}.cache", name));
+    if let Ok(gathered) = read_cache(cache_dir, &path, &header) {
+        return gathered;
+    }
+    let gathered = gather();
+    if let Err(x) = write_cache(&path, &header, &gathered) {
+        eprintln!(
+            "warning: unable to write the registry cache {}: {}",
+            path.display(),
+            x
+        );
+    }
+    gathered
+}
@@ -1,33 +1,274 @@
-use std::{env::args, path::PathBuf};
+use std::{
+    env::args,
+    path::{Path, PathBuf},
+};
 
-use getopts::Options;
+use getopts::{Matches, Options};
 
-use crate::versions::*;
+use crate::{cache::default_registry_cache, config::load_config, versions::*};
 
+#[derive(Clone, Debug)]
 pub struct CmdLine {
     pub version: ActiveVersion,
-    pub xml_path: PathBuf,
+    /// `None` means the bundled registry.
+    pub xml_path: Option<PathBuf>,
     pub extensions: Vec<String>,
     pub use_libc: bool,
     pub used_identifiers_path: Option<PathBuf>,
+    pub group_enums: bool,
+    pub abi: String,
+    pub no_std: bool,
+    pub disabled_extensions_var: Option<String>,
+    pub global: bool,
+    pub typed_handles: bool,
+    pub object_wrappers: bool,
+    pub snake_case: bool,
+    /// If present, constants go in a module of this name, without their
+    /// `GL_` prefix.
+    pub constants_module: Option<String>,
+    /// Whether `Procs` keeps what the driver says about itself.
+    pub driver_info: bool,
+    /// Where to cache what's gathered from registries, if anywhere.
+    pub registry_cache: Option<PathBuf>,
+}
+
+/// A file to generate, and the bindings to put in it.
+pub struct Output {
+    /// `None` means standard output.
+    pub path: Option<PathBuf>,
+    /// One `CmdLine` per target version. They differ only in `version` (and,
+    /// once the registry has been read, in `extensions`).
+    pub targets: Vec<CmdLine>,
+}
+
+/// What we've been asked to do.
+pub enum Mode {
+    /// Generate each of these files.
+    Generate(Vec<Output>),
+    /// Print the differences between two bindings, old then new.
+    Diff(Box<CmdLine>, Box<CmdLine>),
+}
+
+impl CmdLine {
+    /// Describes every option that can change what's gathered from the
+    /// registry, for keying the registry cache.
+    pub fn cache_key(&self) -> String {
+        format!(
+            "{:?}",
+            CmdLine {
+                xml_path: None,
+                used_identifiers_path: None,
+                registry_cache: None,
+                ..self.clone()
+            }
+        )
+    }
+    /// Describes where the registry comes from, for humans.
+    pub fn describe_xml(&self) -> String {
+        match self.xml_path {
+            Some(ref path) => path.display().to_string(),
+            #[cfg(feature = "bundled-registry")]
+            None => format!(
+                "the bundled gl.xml (revision {})",
+                crate::bundled::revision()
+            ),
+            #[cfg(not(feature = "bundled-registry"))]
+            None => unreachable!(),
+        }
+    }
+    /// Checks for combinations of options that don't make sense, and makes
+    /// one copy of these options for each target version.
+    pub fn for_each_version(
+        self,
+        versions: Vec<ActiveVersion>,
+    ) -> Option<Vec<CmdLine>> {
+        if self.global && self.no_std {
+            eprintln!("--global needs thread-local storage, so it can't be used with --no-std");
+            return None;
+        }
+        if self.driver_info && self.no_std {
+            eprintln!("--driver-info keeps what the driver says in `String`s, so it can't be used with --no-std");
+            return None;
+        }
+        Some(
+            versions
+                .into_iter()
+                .map(|version| CmdLine {
+                    version,
+                    ..self.clone()
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Parses a list of target versions, defaulting to gles2.0 if there are
+/// none.
+pub fn parse_versions(versions: &[String]) -> Option<Vec<ActiveVersion>> {
+    let mut ret = Vec::new();
+    for version in versions {
+        match parse_version(version) {
+            Err(wat) => {
+                eprintln!("Invalid glversion: {}", wat);
+                return None;
+            }
+            Ok(version) => ret.push(version),
+        }
+    }
+    if ret.is_empty() {
+        ret.push(parse_version("gles2.0").unwrap());
+    }
+    for (i, version) in ret.iter().enumerate() {
+        if ret[..i]
+            .iter()
+            .any(|x| x.module_name() == version.module_name())
+        {
+            eprintln!("{} was targeted more than once", version);
+            return None;
+        }
+    }
+    Some(ret)
+}
+
+pub fn parse_abi(abi: Option<&str>) -> Option<String> {
+    match abi.unwrap_or("system") {
+        x @ ("system" | "C") => Some(x.to_owned()),
+        x => {
+            eprintln!("Invalid ABI: {} (must be system or C)", x);
+            None
+        }
+    }
+}
+
+pub fn parse_constants_module(name: Option<String>) -> Option<Option<String>> {
+    match name {
+        Some(x)
+            if x.is_empty()
+                || x.starts_with(|x: char| x.is_ascii_digit())
+                || !x
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '_') =>
+        {
+            eprintln!("Invalid constants module name: {}", x);
+            None
+        }
+        x => Some(x),
+    }
+}
+
+pub fn default_disabled_extensions_var() -> String {
+    "GL_DISABLED_EXTENSIONS".to_owned()
 }
 
 fn print_usage(program: &str, opts: &Options) {
     let brief = format!(
-        "Usage: {} [options] path/to/gl.xml extensions... \
-                         >.../gl.rs",
+        "Usage: {0} [options] path/to/gl.xml extensions... \
+                         >.../gl.rs\n       {0} --config rglgen.toml\n       \
+         {0} diff [options] path/to/gl.xml [path/to/new/gl.xml] \
+         extensions...",
         program
     );
     eprint!("{}", opts.usage(&brief));
+    #[cfg(feature = "bundled-registry")]
+    eprintln!(
+        "\nThe path to gl.xml may be left out (if the first extension isn't \
+         also a file), in which case the bundled copy is used.\nIt is from \
+         revision {} of the OpenGL-Registry repository.",
+        crate::bundled::revision()
+    );
+}
+
+/// Takes the path to `gl.xml` off the front of the free arguments. With the
+/// bundled registry, it may be left out, in which case `Some(None)` is
+/// returned. (It's only left out if the first argument is an extension name,
+/// and not also a file, so that a mistyped path isn't taken for an
+/// extension.)
+fn take_xml_path(free: &mut &[String]) -> Option<Option<PathBuf>> {
+    match free.first() {
+        Some(path)
+            if !cfg!(feature = "bundled-registry")
+                || !path.starts_with("GL_")
+                || Path::new(path).exists() =>
+        {
+            *free = &free[1..];
+            Some(Some(PathBuf::from(path)))
+        }
+        _ if cfg!(feature = "bundled-registry") => Some(None),
+        _ => {
+            eprintln!("No gl.xml path specified");
+            None
+        }
+    }
+}
+
+/// Adds the options that affect which identifiers are in a binding, rather
+/// than how they're output.
+fn add_selection_options(opts: &mut Options) {
+    opts.optopt("u", "used-identifiers", "path to a text file that contains identifiers, one per line, that your program uses. If this option is not specified, ALL identifiers will be exposed. Using this option saves a lot of runtime memory and a LOT of compile time, and is STRONGLY RECOMMENDED. If an identifier is in this text file but not found in this version of the GL, it is simply ignored. Blank lines and `#` comments are ignored, `*` and `?` act as wildcards, and `@include PATH` pulls in another such file (relative to the including one).", "PATH");
+    opts.optflag("g", "group-enums", "when used with -u, also expose every enum in a group that a used command takes as a parameter (e.g. using glDrawElements pulls in GL_TRIANGLES and GL_UNSIGNED_SHORT)");
 }
 
-pub fn parse_cmdline() -> Option<CmdLine> {
+fn add_registry_cache_options(opts: &mut Options) {
+    opts.optopt("", "registry-cache", "directory to cache what was gathered from gl.xml in, so that later runs with the same gl.xml and options don't have to parse it again (default is `rglgen-cache` in `OUT_DIR` when run from a build script, and no cache otherwise)", "DIR");
+    opts.optflag(
+        "",
+        "no-registry-cache",
+        "always parse gl.xml from scratch, and don't cache the result",
+    );
+}
+
+fn get_registry_cache(matches: &Matches) -> Option<PathBuf> {
+    if matches.opt_present("no-registry-cache") {
+        None
+    } else {
+        matches
+            .opt_str("registry-cache")
+            .map(PathBuf::from)
+            .or_else(default_registry_cache)
+    }
+}
+
+/// The options that `rglgen.toml` takes the place of. (Everything but
+/// `--config` itself and the registry cache options.)
+const CONFIG_FILE_OPTIONS: &[&str] = &[
+    "target-version",
+    "used-identifiers",
+    "group-enums",
+    "abi",
+    "no-std",
+    "disabled-extensions-var",
+    "no-disabled-extensions-var",
+    "global",
+    "typed-handles",
+    "object-wrappers",
+    "snake-case",
+    "constants-module",
+    "driver-info",
+    "without-libc",
+];
+
+pub fn parse_cmdline() -> Option<Mode> {
     let argv: Vec<String> = args().collect();
+    if argv.get(1).map(String::as_str) == Some("diff") {
+        return parse_diff_cmdline(&argv);
+    }
     let program = &argv[0];
     let mut opts = Options::new();
-    opts.optopt("t", "target-version", "change the targeted API and version (e.g. gl2.1, glcore4.0, gles2.0; default is gles2.0)", "VERSION");
-    opts.optopt("u", "used-identifiers", "path to a text file that contains identifiers, one per line, that your program uses. If this option is not specified, ALL identifiers will be exposed. Using this option saves a lot of runtime memory and a LOT of compile time, and is STRONGLY RECOMMENDED. If an identifier is in this text file but not found in this version of the GL, it is simply ignored.", "PATH");
+    opts.optopt("", "config", "generate every output listed in the given configuration file, instead of taking the options from the command line", "PATH");
+    opts.optmulti("t", "target-version", "change the targeted API and version (e.g. gl2.1, glcore4.0, gles2.0; default is gles2.0). May be given more than once, in which case each binding will be output in its own module (e.g. `gl21`, `glcore40`, `gles20`), sharing a `types` module. Each extension will be included in every binding that supports it.", "VERSION");
+    add_selection_options(&mut opts);
+    opts.optopt("A", "abi", "calling convention of GL entry points and callbacks, either `system` (correct everywhere, including 32-bit Windows) or `C` (default is system)", "ABI");
+    opts.optflag("", "no-std", "generate code that only uses `core`, for `no_std` crates. `Procs::new` will take the list of disabled extensions as a parameter instead of reading GL_DISABLED_EXTENSIONS from the environment");
+    opts.optopt("", "disabled-extensions-var", "name of the environment variable `Procs::new` reads a list of extensions to ignore from (default is GL_DISABLED_EXTENSIONS)", "NAME");
+    opts.optflag("", "no-disabled-extensions-var", "don't let `Procs::new` read a list of extensions to ignore from the environment at all");
+    opts.optflag("", "global", "also generate free functions (e.g. `ClearColor(...)`) that call through a thread-local \"current\" `Procs`, set with `make_current`");
+    opts.optflag("", "typed-handles", "give each class of GL object its own handle type (e.g. `Buffer(GLuint)`, `Texture(GLuint)`), and use them in command signatures, so that mixing them up is a compile error");
+    opts.optflag("", "object-wrappers", "also generate an `objects` module, containing owning wrappers (e.g. `objects::Buffer`) that delete GL objects when dropped, for every class of object that the used commands can both make and delete");
+    opts.optflag("", "snake-case", "name the methods on `Procs` (and the free functions, with --global) in snake_case, e.g. `bind_buffer` instead of `BindBuffer`");
+    opts.optopt("", "constants-module", "put the constants in a module of the given name, without their `GL_` prefix (e.g. `--constants-module gl` gives `gl::TEXTURE_2D`)", "NAME");
+    opts.optflag("", "driver-info", "make `Procs` keep the driver's vendor, renderer, version and GLSL version strings, and the list of extensions it advertises, and show them in its `Debug` output");
     opts.optflag("C", "without-libc", "disable the use of the `libc` crate for correctly matching GL types (dangerous!)");
+    add_registry_cache_options(&mut opts);
     if argv.len() < 2 {
         print_usage(program, &opts);
         return None;
@@ -36,25 +277,134 @@ pub fn parse_cmdline() -> Option<CmdLine> {
         Ok(matches) => matches,
         Err(fail) => panic!("{}", fail.to_string()),
     };
-    if matches.free.is_empty() {
-        eprintln!("No gl.xml path specified");
-        print_usage(program, &opts);
-        return None;
+    if let Some(path) = matches.opt_str("config") {
+        // (everything else comes from the configuration file)
+        if !matches.free.is_empty()
+            || CONFIG_FILE_OPTIONS.iter().any(|x| matches.opt_present(x))
+        {
+            eprintln!("--config can't be combined with other options");
+            return None;
+        }
+        let registry_cache = if matches.opt_present("registry-cache")
+            || matches.opt_present("no-registry-cache")
+        {
+            Some(get_registry_cache(&matches))
+        } else {
+            None
+        };
+        return load_config(&PathBuf::from(path), registry_cache)
+            .map(Mode::Generate);
     }
+    let mut free = &matches.free[..];
+    let xml_path = match take_xml_path(&mut free) {
+        Some(xml_path) => xml_path,
+        None => {
+            print_usage(program, &opts);
+            return None;
+        }
+    };
+    let versions = parse_versions(&matches.opt_strs("t"))?;
     let ret = CmdLine {
-        version: match parse_version(
-            matches.opt_str("t").as_deref().unwrap_or("gles2.0"),
-        ) {
-            Err(wat) => {
-                eprintln!("Invalid glversion: {}", wat);
+        version: versions[0].clone(),
+        xml_path,
+        extensions: free.to_vec(),
+        use_libc: !matches.opt_present("C"),
+        used_identifiers_path: matches.opt_str("u").map(PathBuf::from),
+        group_enums: matches.opt_present("g"),
+        abi: parse_abi(matches.opt_str("A").as_deref())?,
+        no_std: matches.opt_present("no-std"),
+        disabled_extensions_var: if matches
+            .opt_present("no-disabled-extensions-var")
+        {
+            None
+        } else {
+            Some(
+                matches
+                    .opt_str("disabled-extensions-var")
+                    .unwrap_or_else(default_disabled_extensions_var),
+            )
+        },
+        global: matches.opt_present("global"),
+        typed_handles: matches.opt_present("typed-handles"),
+        object_wrappers: matches.opt_present("object-wrappers"),
+        snake_case: matches.opt_present("snake-case"),
+        constants_module: parse_constants_module(
+            matches.opt_str("constants-module"),
+        )?,
+        driver_info: matches.opt_present("driver-info"),
+        registry_cache: get_registry_cache(&matches),
+    };
+    Some(Mode::Generate(vec![Output {
+        path: None,
+        targets: ret.for_each_version(versions)?,
+    }]))
+}
+
+/// Parses the arguments to `rglgen diff`. Either two targets are compared
+/// using the same `gl.xml`, or one target using two different ones.
+fn parse_diff_cmdline(argv: &[String]) -> Option<Mode> {
+    let program = &argv[0];
+    let mut opts = Options::new();
+    opts.optmulti("t", "target-version", "the targeted API and version (default is gles2.0). Give it twice to compare two targets using the same gl.xml, or at most once to compare the same target using two different gl.xml files.", "VERSION");
+    add_selection_options(&mut opts);
+    opts.optflag(
+        "C",
+        "without-libc",
+        "compare types as they would be output without the `libc` crate",
+    );
+    add_registry_cache_options(&mut opts);
+    let matches = match opts.parse(&argv[2..]) {
+        Ok(matches) => matches,
+        Err(fail) => panic!("{}", fail.to_string()),
+    };
+    let versions = parse_versions(&matches.opt_strs("t"))?;
+    let xml_count = match versions.len() {
+        1 => 2,
+        2 => 1,
+        _ => {
+            eprintln!("diff can only compare two targets");
+            return None;
+        }
+    };
+    let mut free = &matches.free[..];
+    let mut xml_paths = Vec::with_capacity(xml_count);
+    for _ in 0..xml_count {
+        match take_xml_path(&mut free) {
+            Some(xml_path) => xml_paths.push(xml_path),
+            None => {
+                print_usage(program, &opts);
                 return None;
             }
-            Ok(version) => version,
-        },
-        xml_path: PathBuf::from(&matches.free[0]),
-        extensions: matches.free[1..].to_vec(),
+        }
+    }
+    let old = CmdLine {
+        version: versions[0].clone(),
+        xml_path: xml_paths[0].clone(),
+        extensions: free.to_vec(),
         use_libc: !matches.opt_present("C"),
         used_identifiers_path: matches.opt_str("u").map(PathBuf::from),
+        group_enums: matches.opt_present("g"),
+        abi: parse_abi(None)?,
+        no_std: false,
+        disabled_extensions_var: None,
+        global: false,
+        typed_handles: false,
+        object_wrappers: false,
+        snake_case: false,
+        constants_module: None,
+        driver_info: false,
+        registry_cache: get_registry_cache(&matches),
     };
-    Some(ret)
+    let new = CmdLine {
+        version: versions[versions.len() - 1].clone(),
+        xml_path: xml_paths.pop().unwrap(),
+        ..old.clone()
+    };
+    if new.version == old.version && new.xml_path == old.xml_path {
+        eprintln!(
+            "diff needs two different targets, or two different gl.xml files"
+        );
+        return None;
+    }
+    Some(Mode::Diff(Box::new(old), Box::new(new)))
 }
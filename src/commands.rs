@@ -1,24 +1,45 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     io::Write,
+    process::exit,
 };
 
-use lazy_static::lazy_static;
-use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cmdline::CmdLine,
+    declarator::*,
     dom::{Element, Node},
+    naming::snake_case,
 };
 
-#[derive(Debug)]
+/// What we know about one of a command's parameters (or its return value),
+/// for recognizing commands that make and delete objects.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParamShape {
+    /// The class of object it names, if any.
+    pub class: Option<String>,
+    /// Its type, minus any pointers. (If it has a handle type, that.)
+    pub base: String,
+    pub pointer: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Command {
     name: String,
+    /// See `method_name`.
+    method: String,
+    /// The command this one is an alias of, if any.
+    alias: Option<String>,
     returns: String,
     params: String,
     ignored_params: String,
     param_names: String,
     param_types: HashSet<String>,
+    groups: HashSet<String>,
+    handles: HashSet<String>,
+    return_shape: ParamShape,
+    param_shapes: Vec<ParamShape>,
 }
 
 impl Command {
@@ -31,144 +52,264 @@ impl Command {
             map.insert(param, ext);
         }
     }
-    pub fn output_imp(&self, _opts: &CmdLine, procid: u32) {
+    /// Returns the names of the enum groups this command's parameters (and
+    /// return value) are drawn from.
+    pub fn get_groups(&self) -> &HashSet<String> {
+        &self.groups
+    }
+    /// Returns the names of the handle types this command's parameters (and
+    /// return value) use. Always empty unless `--typed-handles` was given.
+    pub fn get_handles(&self) -> &HashSet<String> {
+        &self.handles
+    }
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the name of the command this one is an alias of, if any. (An
+    /// extension command is usually an alias of the core command it was
+    /// promoted to.)
+    pub fn get_alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+    /// Returns the parameter list, as in the method's signature.
+    pub fn get_params(&self) -> &str {
+        &self.params
+    }
+    /// Returns the parameter names, separated by commas.
+    pub fn get_param_names(&self) -> &str {
+        &self.param_names
+    }
+    pub fn get_return_shape(&self) -> &ParamShape {
+        &self.return_shape
+    }
+    pub fn get_param_shapes(&self) -> &[ParamShape] {
+        &self.param_shapes
+    }
+    /// The name of the method on `Procs` (and of the free function, in
+    /// global mode) that calls this command.
+    pub fn method_name(&self) -> &str {
+        &self.method
+    }
+    /// The command's prototype, in Rust syntax, e.g.
+    /// `glGetError() -> GLenum`.
+    pub fn signature(&self) -> String {
         if self.returns == "libc::c_void" {
-            println!(
-                r#"    #[inline(always)] pub unsafe fn {}(&self, {}) {{ unsafe {{ (transmute::<_, extern "C" fn({})>(self.procs[{}]))({}) }} }}"#,
-                if self.name.starts_with("gl") {
-                    &(&self.name)[2..]
-                } else {
-                    &(&self.name)[..]
-                },
+            format!("{}({})", self.name, self.params)
+        } else {
+            format!("{}({}) -> {}", self.name, self.params, self.returns)
+        }
+    }
+    pub fn output_imp<W: Write>(
+        &self,
+        opts: &CmdLine,
+        procid: u32,
+        out: &mut W,
+    ) {
+        if self.returns == "libc::c_void" {
+            writeln!(out,
+                r#"    #[inline(always)] pub unsafe fn {}(&self, {}) {{ unsafe {{ (transmute::<_, extern "{}" fn({})>(self.procs[{}]))({}) }} }}"#,
+                self.method_name(),
                 self.params,
+                opts.abi,
                 self.params,
                 procid,
                 self.param_names
-            );
+            ).unwrap();
         } else {
-            println!(
-                r#"    #[inline(always)] pub unsafe fn {}(&self, {}) -> {} {{ unsafe {{ (transmute::<_, extern "C" fn({}) -> {}>(self.procs[{}]))({}) }} }}"#,
-                if self.name.starts_with("gl") {
-                    &(&self.name)[2..]
-                } else {
-                    &(&self.name)[..]
-                },
+            writeln!(out,
+                r#"    #[inline(always)] pub unsafe fn {}(&self, {}) -> {} {{ unsafe {{ (transmute::<_, extern "{}" fn({}) -> {}>(self.procs[{}]))({}) }} }}"#,
+                self.method_name(),
                 self.params,
                 self.returns,
+                opts.abi,
                 self.params,
                 self.returns,
                 procid,
                 self.param_names
-            );
+            ).unwrap();
         }
     }
-    pub fn output_dummy_imp(&self, ext: &str, _opts: &CmdLine) {
-        println!(
-            r#"extern "C" fn {}_null_imp({}) -> {} {{ missing_ext_panic("{}", "{}"); }}"#,
-            self.name, self.ignored_params, self.returns, self.name, ext
-        );
+    pub fn output_global_imp<W: Write>(&self, _opts: &CmdLine, out: &mut W) {
+        if self.returns == "libc::c_void" {
+            writeln!(out,
+                r#"#[inline(always)] pub unsafe fn {}({}) {{ unsafe {{ current_procs().{}({}) }} }}"#,
+                self.method_name(),
+                self.params,
+                self.method_name(),
+                self.param_names
+            ).unwrap();
+        } else {
+            writeln!(out,
+                r#"#[inline(always)] pub unsafe fn {}({}) -> {} {{ unsafe {{ current_procs().{}({}) }} }}"#,
+                self.method_name(),
+                self.params,
+                self.returns,
+                self.method_name(),
+                self.param_names
+            ).unwrap();
+        }
+    }
+    pub fn output_dummy_imp<W: Write>(
+        &self,
+        ext: &str,
+        opts: &CmdLine,
+        out: &mut W,
+    ) {
+        writeln!(out,
+            r#"extern "{}" fn {}_null_imp({}) -> {} {{ missing_ext_panic("{}", "{}"); }}"#,
+            opts.abi,
+            self.name,
+            self.ignored_params,
+            self.returns,
+            self.name,
+            ext
+        ).unwrap();
+    }
+}
+
+/// The name of the handle type for objects of a given `class`, e.g.
+/// `VertexArray` for `vertex array`.
+pub fn handle_type_name(class: &str) -> String {
+    let mut ret = String::new();
+    for word in class.split([' ', '_']) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            ret.extend(first.to_uppercase());
+            ret.push_str(chars.as_str());
+        }
+    }
+    // `Sync` would shadow the marker trait for anyone who glob-imports us
+    if ret == "Sync" {
+        ret.push_str("Object");
+    }
+    ret
+}
+
+/// Parses the C declaration in a `<proto>` or `<param>`.
+fn parse_element(element: &Element) -> Declaration {
+    let text = element.get_text_as_bytes();
+    let text = String::from_utf8_lossy(&text);
+    match parse_declaration(&text) {
+        Ok(declaration) if declaration.name.is_some() => declaration,
+        Ok(_) => panic!("`{}` doesn't name anything", text),
+        Err(why) => panic!("We couldn't understand `{}`: {}", text, why),
     }
 }
 
-// Simpler than the logic in types.rs. Our types parsing is API/version
-// dependent, while our commands parsing is not.
+fn param_shape(
+    element: &Element,
+    ty: &CType,
+    handle: Option<&str>,
+) -> ParamShape {
+    ParamShape {
+        class: element.get_attributes().get("class").cloned(),
+        base: handle
+            .or_else(|| ty.base_name())
+            .unwrap_or_default()
+            .to_owned(),
+        pointer: ty.is_pointer(),
+    }
+}
+
+/// Writes a parameter or return type, using `handle` in place of the named
+/// type it ultimately refers to, if given.
 fn write_type(
     opts: &CmdLine,
     out: &mut Vec<u8>,
-    mut ptype: &[u8],
+    ty: &CType,
     param_types: &mut HashSet<String>,
+    handle: Option<&str>,
 ) {
-    let ptrkind: &[u8] = if ptype.starts_with(b"const ") {
-        ptype = &ptype[6..];
-        b"*const"
-    } else {
-        b"*mut"
-    };
-    let mut pointer_levels: Vec<&[u8]> = Vec::new();
-    if ptype.ends_with(b"*") {
-        if ptype.ends_with(b"const*") {
-            pointer_levels.push(b"*const");
-            ptype = &ptype[..ptype.len() - 6];
-        } else {
-            pointer_levels.push(ptrkind);
-            ptype = &ptype[..ptype.len() - 1];
+    let base = ty.base_name();
+    let rust = ty.to_rust(opts, &mut |name| {
+        param_types.insert(name.to_owned());
+        match handle {
+            Some(handle) if Some(name) == base => handle.to_owned(),
+            _ => rust_type_name(name),
         }
-        while ptype.ends_with(b"*") {
-            if ptype.ends_with(b"const*") {
-                pointer_levels.push(b"*const");
-                ptype = &ptype[..ptype.len() - 6];
-            } else {
-                pointer_levels.push(b"*mut");
-                ptype = &ptype[..ptype.len() - 1];
+    });
+    out.write_all(rust.as_bytes()).unwrap();
+}
+
+/// Returns the name a `<command>` declares in its `<proto>`.
+fn command_name(tag: &Element) -> String {
+    tag.get_children()
+        .iter()
+        .find_map(|child| match child {
+            Node::Element(element) if element.get_name() == "proto" => {
+                parse_element(element).name
             }
-        }
-    }
-    if !pointer_levels.is_empty() {
-        for level in pointer_levels.into_iter().rev() {
-            out.write_all(level).unwrap();
-        }
-        out.push(b' ');
-    }
-    while ptype.ends_with(b" ") {
-        ptype = &ptype[..ptype.len() - 1];
-    }
-    if ptype == b"void" {
-        if opts.use_libc {
-            out.write_all(b"libc::c_void").unwrap();
-        } else {
-            out.write_all(b"()").unwrap();
-        }
-    } else {
-        param_types.insert(String::from_utf8(ptype.to_vec()).unwrap());
-        out.write_all(ptype).unwrap();
-    }
+            _ => None,
+        })
+        .unwrap()
 }
 
 fn gather_command(
     tag: &Element,
+    name: String,
     opts: &CmdLine,
     map: &mut HashMap<String, Command>,
+    handle_map: &HashMap<String, String>,
 ) {
-    let mut name: Option<String> = None;
+    let mut alias = None;
     let mut returns: Option<String> = None;
+    let mut return_shape = None;
+    let mut param_shapes = Vec::new();
     let mut params = Vec::new();
     let mut ignored_params = Vec::new();
     let mut param_names = Vec::new();
     let mut param_types = HashSet::new();
-    let mut _param_count = 0;
-    lazy_static! {
-        static ref TYPE_AND_NAME_EXTRACTOR: Regex =
-            Regex::new(r#"^(.+?)([_a-zA-Z][_a-zA-Z0-9]*)((?:\[[0-9]+\])?)$"#)
-                .unwrap();
-    }
+    let mut groups = HashSet::new();
+    let mut handles = HashSet::new();
+    let mut get_handle = |element: &Element, ty: &CType| {
+        if !opts.typed_handles {
+            return None;
+        }
+        let class = element.get_attributes().get("class")?;
+        let handle = handle_type_name(class);
+        // (Only commands that aren't bound can disagree with the type
+        // `decide_handle_types` settled on.)
+        if handle_map.get(&handle).map(String::as_str) != ty.base_name() {
+            return None;
+        }
+        handles.insert(handle.clone());
+        Some(handle)
+    };
     for child in tag.get_children() {
         if let Node::Element(ref element) = child {
+            if element.get_name() == "proto" || element.get_name() == "param" {
+                if let Some(group) = element.get_attributes().get("group") {
+                    groups.insert(group.clone());
+                }
+            }
             if element.get_name() == "proto" {
-                assert!(name.is_none());
-                // we could parse out the <ptype> and <name> elements, but
-                // this way is simpler to write
-                let text = element.get_text_as_bytes();
-                let caps =
-                    TYPE_AND_NAME_EXTRACTOR.captures(&text[..]).unwrap();
-                name = Some(String::from_utf8(caps[2].to_vec()).unwrap());
+                let declaration = parse_element(element);
                 let mut rtype = Vec::new();
-                write_type(opts, &mut rtype, &caps[1], &mut param_types);
+                let handle = get_handle(element, &declaration.ty);
+                write_type(
+                    opts,
+                    &mut rtype,
+                    &declaration.ty,
+                    &mut param_types,
+                    handle.as_deref(),
+                );
                 returns = Some(String::from_utf8(rtype).unwrap());
+                return_shape = Some(param_shape(
+                    element,
+                    &declaration.ty,
+                    handle.as_deref(),
+                ));
             } else if element.get_name() == "param" {
-                let text = element.get_text_as_bytes();
-                let caps =
-                    TYPE_AND_NAME_EXTRACTOR.captures(&text[..]).unwrap();
-                let pname = &caps[2];
-                let pname: &[u8] = match pname {
-                    b"type" => b"r#type",
-                    b"ref" => b"r#ref",
-                    x => x,
-                };
-                let mut ptype = caps[1].to_owned();
-                if !caps[3].is_empty() {
-                    ptype.push(b'*');
-                }
-                _param_count += 1;
+                let declaration = parse_element(element);
+                let pname = rust_identifier(&declaration.name.unwrap());
+                let pname = pname.as_bytes();
+                let ty = declaration.ty.decay();
+                let handle = get_handle(element, &ty);
+                param_shapes.push(param_shape(
+                    element,
+                    &ty,
+                    handle.as_deref(),
+                ));
                 if !params.is_empty() {
                     params.write_all(b", ").unwrap();
                     ignored_params.write_all(b", ").unwrap();
@@ -179,49 +320,155 @@ fn gather_command(
                 ignored_params.push(b'_');
                 params.write_all(b": ").unwrap();
                 ignored_params.write_all(b": ").unwrap();
-                write_type(opts, &mut params, &ptype, &mut param_types);
+                write_type(
+                    opts,
+                    &mut params,
+                    &ty,
+                    &mut param_types,
+                    handle.as_deref(),
+                );
                 write_type(
                     opts,
                     &mut ignored_params,
-                    &ptype,
+                    &ty,
                     &mut param_types,
+                    handle.as_deref(),
                 );
+            } else if element.get_name() == "alias" {
+                alias = Some(element.get_attributes()["name"].clone());
             }
         }
     }
+    let method = name.strip_prefix("gl").unwrap_or(&name);
+    let method = if opts.snake_case {
+        snake_case(method)
+    } else {
+        method.to_owned()
+    };
     let result = Command {
-        name: name.unwrap(),
+        name,
+        method,
+        alias,
         returns: returns.unwrap(),
         params: unsafe { String::from_utf8_unchecked(params) },
         ignored_params: unsafe { String::from_utf8_unchecked(ignored_params) },
         param_names: unsafe { String::from_utf8_unchecked(param_names) },
         param_types,
+        groups,
+        handles,
+        return_shape: return_shape.unwrap(),
+        param_shapes,
     };
     map.insert(result.name.clone(), result);
 }
 
+/// Works out which type each class of object is handled by, from the bound
+/// commands that use it. The commands that make and delete objects decide,
+/// if any are bound; every other bound command has to agree with them, or
+/// else there'd be no one type the handle could wrap.
+fn decide_handle_types(
+    commands: &[(String, &Element)],
+    bound: &HashMap<&str, &str>,
+) -> HashMap<String, String> {
+    // class -> (command, base type, whether it makes or deletes objects)
+    let mut uses: BTreeMap<&str, Vec<(&str, String, bool)>> = BTreeMap::new();
+    for (name, tag) in commands {
+        if !bound.contains_key(name.as_str()) {
+            continue;
+        }
+        let decides = ["glGen", "glCreate", "glDelete"]
+            .iter()
+            .any(|verb| name.starts_with(verb));
+        for child in tag.get_children() {
+            if let Node::Element(ref element) = child {
+                if element.get_name() != "proto"
+                    && element.get_name() != "param"
+                {
+                    continue;
+                }
+                let class = match element.get_attributes().get("class") {
+                    Some(class) => class,
+                    None => continue,
+                };
+                let declaration = parse_element(element);
+                if let Some(base) = declaration.ty.decay().base_name() {
+                    uses.entry(class).or_default().push((
+                        name,
+                        base.to_owned(),
+                        decides,
+                    ));
+                }
+            }
+        }
+    }
+    let mut handle_map = HashMap::new();
+    let mut errors = false;
+    for (class, uses) in uses {
+        let (decider, expected, _) = uses
+            .iter()
+            .find(|(_, _, decides)| *decides)
+            .unwrap_or(&uses[0]);
+        for (name, base, _) in &uses {
+            if base != expected {
+                eprintln!(
+                    "{} uses {} for {} objects, but {} uses {}",
+                    name, base, class, decider, expected
+                );
+                errors = true;
+            }
+        }
+        handle_map.insert(handle_type_name(class), expected.clone());
+    }
+    if errors {
+        eprintln!("Errors have occurred, panicking");
+        exit(1);
+    }
+    handle_map
+}
+
+/// Also returns the handle types the commands use, mapped to the types they
+/// wrap. `bound` is the set of commands that go into the binding; only they
+/// get a say in what the handle types wrap.
+#[allow(clippy::type_complexity)]
 pub fn gather_commands(
     root: &Element,
     opts: &CmdLine,
-) -> (HashMap<String, Command>, Vec<String>) {
-    let mut map = HashMap::new();
+    bound: &HashMap<&str, &str>,
+) -> (
+    HashMap<String, Command>,
+    Vec<String>,
+    HashMap<String, String>,
+) {
+    let mut commands = Vec::new();
     for child in root.get_children() {
         if let Node::Element(ref element) = child {
             if element.get_name() == "commands" {
                 for child in element.get_children() {
                     if let Node::Element(ref element) = child {
                         if element.get_name() == "command" {
-                            gather_command(element, opts, &mut map)
+                            commands.push((command_name(element), element));
                         }
                     }
                 }
             }
         }
     }
+    // (sorted, so that which command is blamed for a disagreement doesn't
+    // depend on where it is in gl.xml)
+    commands.sort_by(|a, b| a.0.cmp(&b.0));
+    let handle_map = if opts.typed_handles {
+        decide_handle_types(&commands, bound)
+    } else {
+        HashMap::new()
+    };
+    let mut map = HashMap::new();
+    for (name, element) in commands {
+        gather_command(element, name, opts, &mut map, &handle_map);
+    }
     let mut order = Vec::new();
     for key in map.keys() {
         order.push((*key).clone());
     }
     order.sort();
-    (map, order)
+    (map, order, handle_map)
 }
@@ -18,12 +18,16 @@ fn gather_comment_elements<W: io::Write>(root: &Element, out: &mut W) {
     }
 }
 
-pub fn output_comment_elements(root: &Element) {
+/// Returns the text of every `<comment>` in the registry.
+pub fn gather_comments(root: &Element) -> String {
     let mut comment_text = Vec::new();
     gather_comment_elements(root, &mut comment_text);
-    let comment_text = String::from_utf8(comment_text).unwrap();
+    String::from_utf8(comment_text).unwrap()
+}
+
+pub fn output_comments<W: io::Write>(comment_text: &str, out: &mut W) {
     if !comment_text.starts_with('\n') {
-        println!("//");
+        writeln!(out, "//").unwrap();
     }
     for line in comment_text.split('\n') {
         let mut line = line;
@@ -31,9 +35,9 @@ pub fn output_comment_elements(root: &Element) {
             line = &line[..line.len() - 1];
         }
         if line.is_empty() {
-            println!("//");
+            writeln!(out, "//").unwrap();
         } else {
-            println!("// {}", line);
+            writeln!(out, "// {}", line).unwrap();
         }
     }
 }
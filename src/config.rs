@@ -0,0 +1,140 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{cache::default_registry_cache, cmdline::*};
+
+/// The contents of an `rglgen.toml`. Relative paths in it are relative to
+/// the directory containing it.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ConfigFile {
+    /// Path to `gl.xml`, used by every output that doesn't give its own. (If
+    /// there is none, the bundled registry is used, if there is one.)
+    xml: Option<String>,
+    registry_cache: Option<String>,
+    #[serde(default)]
+    no_registry_cache: bool,
+    #[serde(rename = "output")]
+    outputs: Vec<OutputConfig>,
+}
+
+/// One `[[output]]` table. Each option means the same thing as the command
+/// line option of the same name.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct OutputConfig {
+    path: String,
+    xml: Option<String>,
+    #[serde(default)]
+    target_version: Vec<String>,
+    #[serde(default)]
+    extensions: Vec<String>,
+    used_identifiers: Option<String>,
+    #[serde(default)]
+    group_enums: bool,
+    abi: Option<String>,
+    #[serde(default)]
+    without_libc: bool,
+    #[serde(default)]
+    no_std: bool,
+    disabled_extensions_var: Option<String>,
+    #[serde(default)]
+    no_disabled_extensions_var: bool,
+    #[serde(default)]
+    global: bool,
+    #[serde(default)]
+    typed_handles: bool,
+    #[serde(default)]
+    object_wrappers: bool,
+    #[serde(default)]
+    snake_case: bool,
+    constants_module: Option<String>,
+    #[serde(default)]
+    driver_info: bool,
+}
+
+/// `registry_cache`, if given, is used in place of the file's own registry
+/// cache settings.
+pub fn load_config(
+    path: &Path,
+    registry_cache: Option<Option<PathBuf>>,
+) -> Option<Vec<Output>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(x) => {
+            eprintln!("{}: {}", path.display(), x);
+            return None;
+        }
+    };
+    let config: ConfigFile = match toml::from_str(&text) {
+        Ok(config) => config,
+        Err(x) => {
+            eprintln!("{}: {}", path.display(), x);
+            return None;
+        }
+    };
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let registry_cache = if let Some(registry_cache) = registry_cache {
+        registry_cache
+    } else if config.no_registry_cache {
+        None
+    } else {
+        config
+            .registry_cache
+            .map(|x| base.join(x))
+            .or_else(default_registry_cache)
+    };
+    let mut ret = Vec::with_capacity(config.outputs.len());
+    for output in config.outputs {
+        let xml_path = match output.xml.as_ref().or(config.xml.as_ref()) {
+            Some(xml) => Some(base.join(xml)),
+            None if cfg!(feature = "bundled-registry") => None,
+            None => {
+                eprintln!(
+                    "{}: no gl.xml path specified for {}",
+                    path.display(),
+                    output.path
+                );
+                return None;
+            }
+        };
+        let versions = parse_versions(&output.target_version)?;
+        let opts = CmdLine {
+            version: versions[0].clone(),
+            xml_path,
+            extensions: output.extensions,
+            use_libc: !output.without_libc,
+            used_identifiers_path: output
+                .used_identifiers
+                .map(|x| base.join(x)),
+            group_enums: output.group_enums,
+            abi: parse_abi(output.abi.as_deref())?,
+            no_std: output.no_std,
+            disabled_extensions_var: if output.no_disabled_extensions_var {
+                None
+            } else {
+                Some(
+                    output
+                        .disabled_extensions_var
+                        .unwrap_or_else(default_disabled_extensions_var),
+                )
+            },
+            global: output.global,
+            typed_handles: output.typed_handles,
+            object_wrappers: output.object_wrappers,
+            snake_case: output.snake_case,
+            constants_module: parse_constants_module(output.constants_module)?,
+            driver_info: output.driver_info,
+            registry_cache: registry_cache.clone(),
+        };
+        ret.push(Output {
+            path: Some(base.join(output.path)),
+            targets: opts.for_each_version(versions)?,
+        });
+    }
+    Some(ret)
+}
@@ -0,0 +1,214 @@
+use std::io::Write;
+
+use crate::{cmdline::CmdLine, commands::Command};
+
+/// The commands that can install a debug callback, best first. They all take
+/// the same parameters, and their callback types are all the same.
+const DEBUG_CALLBACK_COMMANDS: &[&str] = &[
+    "glDebugMessageCallback",
+    "glDebugMessageCallbackKHR",
+    "glDebugMessageCallbackARB",
+];
+
+/// An enum's name, its doc comment, and its variants with their values.
+type DebugEnum = (&'static str, &'static str, &'static [(&'static str, u32)]);
+
+/// Enums that a debug message is decoded into, with their variants and
+/// values. The values are the same in core, `KHR_debug` and
+/// `ARB_debug_output`. (`ARB_debug_output` lacks the last few types.)
+const DEBUG_ENUMS: &[DebugEnum] = &[
+    (
+        "DebugSource",
+        "Where a debug message came from.",
+        &[
+            ("Api", 0x8246),
+            ("WindowSystem", 0x8247),
+            ("ShaderCompiler", 0x8248),
+            ("ThirdParty", 0x8249),
+            ("Application", 0x824A),
+            ("Other", 0x824B),
+        ],
+    ),
+    (
+        "DebugType",
+        "What a debug message is about.",
+        &[
+            ("Error", 0x824C),
+            ("DeprecatedBehavior", 0x824D),
+            ("UndefinedBehavior", 0x824E),
+            ("Portability", 0x824F),
+            ("Performance", 0x8250),
+            ("Other", 0x8251),
+            ("Marker", 0x8268),
+            ("PushGroup", 0x8269),
+            ("PopGroup", 0x826A),
+        ],
+    ),
+    (
+        "DebugSeverity",
+        "How serious a debug message is.",
+        &[
+            ("High", 0x9146),
+            ("Medium", 0x9147),
+            ("Low", 0x9148),
+            ("Notification", 0x826B),
+        ],
+    ),
+];
+
+/// Returns the command that `Procs::set_debug_callback` should call, if
+/// there is one. The closure has to be boxed, so there is none with
+/// `--no-std`.
+pub fn find_debug_callback<'a>(
+    opts: &CmdLine,
+    used_commands: &[&'a str],
+) -> Option<&'a str> {
+    if opts.no_std {
+        return None;
+    }
+    DEBUG_CALLBACK_COMMANDS
+        .iter()
+        .find_map(|x| used_commands.iter().find(|y| *y == x).cloned())
+}
+
+/// Outputs the field of `Procs` that keeps the debug callback alive.
+pub fn output_debug_callback_field<W: Write>(out: &mut W) {
+    writeln!(
+        out,
+        "    debug_callback: std::cell::Cell<Option<Box<DebugCallback>>>,"
+    )
+    .unwrap();
+}
+
+/// Outputs `DebugMessage` and friends, the trampoline, and
+/// `Procs::set_debug_callback`, which installs the trampoline with `command`.
+pub fn output_debug_callback<W: Write>(
+    opts: &CmdLine,
+    command: &Command,
+    out: &mut W,
+) {
+    for (name, doc, variants) in DEBUG_ENUMS {
+        writeln!(
+            out,
+            r#"
+/// {}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum {} {{"#,
+            doc, name
+        )
+        .unwrap();
+        for (variant, _) in variants.iter() {
+            writeln!(out, "    {},", variant).unwrap();
+        }
+        write!(
+            out,
+            r#"    /// A value this binding doesn't know about.
+    Unknown(GLenum),
+}}
+
+impl {} {{
+    pub fn from_raw(raw: GLenum) -> Self {{
+        match raw {{
+"#,
+            name
+        )
+        .unwrap();
+        for (variant, value) in variants.iter() {
+            writeln!(
+                out,
+                "            0x{:04X} => {}::{},",
+                value, name, variant
+            )
+            .unwrap();
+        }
+        write!(
+            out,
+            r#"            raw => {}::Unknown(raw),
+        }}
+    }}
+}}
+"#,
+            name
+        )
+        .unwrap();
+    }
+    let void = if opts.use_libc { "libc::c_void" } else { "()" };
+    write!(
+        out,
+        r#"
+/// A message from the GL's debug output. See [`Procs::set_debug_callback`].
+#[derive(Clone, Copy, Debug)]
+pub struct DebugMessage<'a> {{
+    pub source: DebugSource,
+    pub kind: DebugType,
+    pub id: GLuint,
+    pub severity: DebugSeverity,
+    /// The message itself. (If the driver sends invalid UTF-8, this stops
+    /// just short of it.)
+    pub message: &'a str,
+}}
+
+pub type DebugCallback = Box<dyn Fn(DebugMessage)>;
+
+extern "{abi}" fn debug_trampoline(source: GLenum, r#type: GLenum, id: GLuint, severity: GLenum, length: GLsizei, message: *const GLchar, user_param: *const {void}) {{
+    // Unsafe justification: `set_debug_callback` passes a pointer to the
+    // callback it keeps alive, and the GL passes a message of the given
+    // length (or, from some drivers, a null-terminated one)
+    let (callback, message) = unsafe {{
+        let callback = &*(user_param as *const DebugCallback);
+        let message = if length < 0 {{
+            CStr::from_ptr(message as *const _).to_bytes()
+        }} else {{
+            std::slice::from_raw_parts(message as *const u8, length as usize)
+        }};
+        (callback, message)
+    }};
+    let message = match std::str::from_utf8(message) {{
+        Ok(x) => x,
+        Err(x) => std::str::from_utf8(&message[..x.valid_up_to()]).unwrap(),
+    }};
+    callback(DebugMessage {{
+        source: DebugSource::from_raw(source),
+        kind: DebugType::from_raw(r#type),
+        id,
+        severity: DebugSeverity::from_raw(severity),
+        message,
+    }});
+}}
+
+impl Procs {{
+    /// Makes the GL call `callback` with every debug message it produces
+    /// (using `{command}`), replacing any callback installed before. Pass
+    /// `None` to remove it.
+    ///
+    /// You may still need to enable `GL_DEBUG_OUTPUT`, or make a debug
+    /// context, before the GL produces any messages.
+    ///
+    /// # Safety
+    ///
+    /// This `Procs`'s context must be current. Don't call this from inside
+    /// the callback. Remove the callback (or destroy the context) before
+    /// dropping this `Procs`. A panic inside the callback will abort.
+    pub unsafe fn set_debug_callback(&self, callback: Option<DebugCallback>) {{
+        let callback = callback.map(Box::new);
+        let user_param = match callback {{
+            Some(ref x) => &**x as *const DebugCallback as *const {void},
+            None => std::ptr::null(),
+        }};
+        let trampoline = match callback {{
+            Some(_) => Some(debug_trampoline as _),
+            None => None,
+        }};
+        unsafe {{ self.{method}(trampoline, user_param) }};
+        // (the GL is done with the old callback now, so drop it)
+        self.debug_callback.set(callback);
+    }}
+}}
+"#,
+        abi = opts.abi,
+        void = void,
+        command = command.get_name(),
+        method = command.method_name(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,762 @@
+//! A parser for the small subset of C declarations that appears in
+//! `gl.xml`, and their translation into Rust types.
+
+use std::{collections::HashMap, fmt};
+
+use lazy_static::lazy_static;
+
+use crate::cmdline::CmdLine;
+
+/// A C type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CType {
+    /// A builtin type (like `unsigned int`), a typedef name (like `GLuint`),
+    /// or a struct (like `struct _cl_event`).
+    Named {
+        name: String,
+        is_const: bool,
+    },
+    /// `is_const` is whether the pointer itself is `const`, as in
+    /// `GLchar *const`, not whether what it points to is.
+    Pointer {
+        to: Box<CType>,
+        is_const: bool,
+    },
+    Array {
+        of: Box<CType>,
+        len: u64,
+    },
+    Function {
+        returns: Box<CType>,
+        params: Vec<Declaration>,
+    },
+}
+
+/// A type, and the name it's declared with (if any; parameters and
+/// `struct X;` don't have to name anything).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    pub name: Option<String>,
+    pub ty: CType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Punct(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(x) => write!(f, "`{}`", x),
+            Token::Number(x) => write!(f, "`{}`", x),
+            Token::Punct(x) => write!(f, "`{}`", x),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut ret = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '_' || c.is_ascii_alphabetic() {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek() {
+                if c != '_' && !c.is_ascii_alphanumeric() {
+                    break;
+                }
+                ident.push(c);
+                chars.next();
+            }
+            ret.push(Token::Ident(ident));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_alphanumeric() {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            let value = match number.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => number.parse(),
+            };
+            match value {
+                Ok(x) => ret.push(Token::Number(x)),
+                Err(_) => return Err(format!("bad number `{}`", number)),
+            }
+        } else if "*()[],;".contains(c) {
+            ret.push(Token::Punct(c));
+            chars.next();
+        } else {
+            return Err(format!("unsupported character `{}`", c));
+        }
+    }
+    Ok(ret)
+}
+
+/// Words that make up builtin C types, which (unlike typedef names) can be
+/// combined.
+const BUILTIN_WORDS: &[&str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed",
+    "unsigned",
+];
+
+/// One step from the type named by the specifiers towards the declared type.
+enum Step {
+    Pointer(bool),
+    Array(u64),
+    Function(Vec<Declaration>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn peek_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+    fn expect_punct(&mut self, c: char) -> Result<(), String> {
+        if self.peek_punct(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", c)))
+        }
+    }
+    fn is_apientry(&self, pos: usize) -> bool {
+        matches!(self.tokens.get(pos), Some(Token::Ident(x)) if x == "APIENTRY")
+    }
+    fn unexpected(&self, wanted: &str) -> String {
+        match self.peek() {
+            Some(token) => format!("expected {}, found {}", wanted, token),
+            None => format!("expected {}, found the end", wanted),
+        }
+    }
+    /// Parses the type specifiers and qualifiers at the start of a
+    /// declaration, like `const unsigned int` or `struct _cl_event`.
+    fn specifiers(&mut self) -> Result<CType, String> {
+        let mut is_const = false;
+        let mut words: Vec<String> = Vec::new();
+        let mut typedef_name = None;
+        while let Some(Token::Ident(ident)) = self.peek() {
+            let ident = ident.clone();
+            match ident.as_str() {
+                "const" => is_const = true,
+                "volatile" | "restrict" | "static" | "extern" | "inline"
+                | "typedef" => {
+                    return Err(format!("`{}` isn't supported", ident))
+                }
+                "struct" | "union" | "enum" => {
+                    if typedef_name.is_some() || !words.is_empty() {
+                        return Err(format!("misplaced `{}`", ident));
+                    }
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(Token::Ident(tag)) => {
+                            typedef_name = Some(format!("{} {}", ident, tag))
+                        }
+                        _ => return Err(self.unexpected("a name")),
+                    }
+                }
+                x if BUILTIN_WORDS.contains(&x) => {
+                    if typedef_name.is_some() {
+                        return Err(format!("misplaced `{}`", ident));
+                    }
+                    words.push(ident);
+                }
+                // anything else is either a typedef name, or (if we already
+                // have a type) the name being declared
+                _ if typedef_name.is_none() && words.is_empty() => {
+                    typedef_name = Some(ident)
+                }
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let name = match typedef_name {
+            Some(name) => name,
+            None if words.is_empty() => return Err(self.unexpected("a type")),
+            None => builtin_name(&words),
+        };
+        Ok(CType::Named { name, is_const })
+    }
+    /// Parses a (possibly abstract) declarator, returning the name it
+    /// declares and the steps from the specified type to the declared one.
+    fn declarator(&mut self) -> Result<(Option<String>, Vec<Step>), String> {
+        if self.peek_punct('*') {
+            self.pos += 1;
+            let mut is_const = false;
+            while let Some(Token::Ident(ident)) = self.peek() {
+                match ident.as_str() {
+                    "const" => is_const = true,
+                    "volatile" | "restrict" => {
+                        return Err(format!("`{}` isn't supported", ident))
+                    }
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+            let (name, mut steps) = self.declarator()?;
+            steps.insert(0, Step::Pointer(is_const));
+            return Ok((name, steps));
+        }
+        let (name, inner) = match self.peek() {
+            // `(` starts either a nested declarator or, in an abstract
+            // declarator, a parameter list
+            Some(Token::Punct('('))
+                if matches!(
+                    self.tokens.get(self.pos + 1),
+                    Some(Token::Punct('*')) | Some(Token::Punct('('))
+                ) || self.is_apientry(self.pos + 1) =>
+            {
+                self.pos += 1;
+                // (the C headers put `APIENTRY` here, where gl.xml has an
+                // `<apientry/>`; the calling convention comes from `--abi`)
+                if self.is_apientry(self.pos) {
+                    self.pos += 1;
+                }
+                let ret = self.declarator()?;
+                self.expect_punct(')')?;
+                ret
+            }
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                (Some(ident), Vec::new())
+            }
+            _ => (None, Vec::new()),
+        };
+        let mut suffixes = Vec::new();
+        loop {
+            if self.peek_punct('[') {
+                self.pos += 1;
+                let len = match self.peek() {
+                    Some(&Token::Number(x)) => x,
+                    _ => return Err(self.unexpected("an array length")),
+                };
+                self.pos += 1;
+                self.expect_punct(']')?;
+                suffixes.push(Step::Array(len));
+            } else if self.peek_punct('(') {
+                self.pos += 1;
+                suffixes.push(Step::Function(self.params()?));
+            } else {
+                break;
+            }
+        }
+        // `a[2][3]` is an array of two arrays of three, so the last suffix
+        // is the closest to the specified type
+        suffixes.reverse();
+        suffixes.extend(inner);
+        Ok((name, suffixes))
+    }
+    /// Parses a parameter list, after its `(`.
+    fn params(&mut self) -> Result<Vec<Declaration>, String> {
+        let mut ret = Vec::new();
+        if self.tokens.get(self.pos) == Some(&Token::Ident("void".to_owned()))
+            && self.tokens.get(self.pos + 1) == Some(&Token::Punct(')'))
+        {
+            self.pos += 2;
+            return Ok(ret);
+        }
+        if self.peek_punct(')') {
+            return Err(
+                "parameter lists must say `void`, not be empty".to_owned()
+            );
+        }
+        loop {
+            ret.push(self.declaration()?);
+            if self.peek_punct(',') {
+                self.pos += 1;
+            } else {
+                self.expect_punct(')')?;
+                return Ok(ret);
+            }
+        }
+    }
+    fn declaration(&mut self) -> Result<Declaration, String> {
+        let mut ty = self.specifiers()?;
+        let (name, steps) = self.declarator()?;
+        for step in steps {
+            ty = match step {
+                Step::Pointer(is_const) => CType::Pointer {
+                    to: Box::new(ty),
+                    is_const,
+                },
+                Step::Array(len) => CType::Array {
+                    of: Box::new(ty),
+                    len,
+                },
+                Step::Function(params) => CType::Function {
+                    returns: Box::new(ty),
+                    params,
+                },
+            };
+        }
+        Ok(Declaration { name, ty })
+    }
+}
+
+/// Puts the words of a builtin type in the order (and the form) that the
+/// type tables use, e.g. `int unsigned` becomes `unsigned int`.
+fn builtin_name(words: &[String]) -> String {
+    let has = |x: &str| words.iter().any(|y| y == x);
+    let mut ret: Vec<&str> = Vec::new();
+    if has("unsigned") {
+        ret.push("unsigned");
+    } else if has("signed") && has("char") {
+        ret.push("signed");
+    }
+    for word in ["void", "char", "short", "long", "float", "double"] {
+        for _ in words.iter().filter(|x| *x == word) {
+            ret.push(word);
+        }
+    }
+    // `int` is implied by `short`, `long`, and a lone `signed`/`unsigned`
+    if has("int") && !has("short") && !has("long")
+        || ret.is_empty()
+        || ret == ["unsigned"]
+    {
+        ret.push("int");
+    }
+    ret.join(" ")
+}
+
+/// Parses a C declaration, like `const GLchar *const*string` or
+/// `void (*GLDEBUGPROC)(GLenum source, ...);`.
+pub fn parse_declaration(text: &str) -> Result<Declaration, String> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    let ret = parser.declaration()?;
+    if parser.peek_punct(';') {
+        parser.pos += 1;
+    }
+    if parser.pos != parser.tokens.len() {
+        return Err(parser.unexpected("the end"));
+    }
+    Ok(ret)
+}
+
+impl CType {
+    fn is_const(&self) -> bool {
+        match self {
+            CType::Named { is_const, .. } => *is_const,
+            CType::Pointer { is_const, .. } => *is_const,
+            CType::Array { of, .. } => of.is_const(),
+            CType::Function { .. } => false,
+        }
+    }
+    /// Whether this is a pointer (or an array, which will become one if it's
+    /// a parameter).
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, CType::Pointer { .. } | CType::Array { .. })
+    }
+    /// The named type this ultimately points to, e.g. `GLuint` for
+    /// `const GLuint *`.
+    pub fn base_name(&self) -> Option<&str> {
+        match self {
+            CType::Named { name, .. } => Some(name),
+            CType::Pointer { to, .. } => to.base_name(),
+            CType::Array { of, .. } => of.base_name(),
+            CType::Function { .. } => None,
+        }
+    }
+    /// Whether this is plain `void`, as a return type.
+    pub fn is_void(&self) -> bool {
+        matches!(self, CType::Named { name, .. } if name == "void")
+    }
+    /// The type a parameter of this type really has: arrays and functions
+    /// decay into pointers.
+    pub fn decay(self) -> CType {
+        match self {
+            CType::Array { of, .. } => CType::Pointer {
+                to: of,
+                is_const: false,
+            },
+            x @ CType::Function { .. } => CType::Pointer {
+                to: Box::new(x),
+                is_const: false,
+            },
+            x => x,
+        }
+    }
+    /// Translates this type into Rust. Builtin types are translated here;
+    /// every other named type is passed to `resolve`.
+    pub fn to_rust(
+        &self,
+        opts: &CmdLine,
+        resolve: &mut dyn FnMut(&str) -> String,
+    ) -> String {
+        match self {
+            CType::Named { name, .. } => match builtin_type(name, opts) {
+                Some(x) => x.to_owned(),
+                None => resolve(name),
+            },
+            CType::Pointer { to, .. } => match **to {
+                // function pointers are nullable in C, but not in Rust
+                CType::Function { .. } => {
+                    format!("Option<{}>", to.to_rust(opts, resolve))
+                }
+                _ => format!(
+                    "*{} {}",
+                    if to.is_const() { "const" } else { "mut" },
+                    to.to_rust(opts, resolve)
+                ),
+            },
+            CType::Array { of, len } => {
+                format!("[{}; {}]", of.to_rust(opts, resolve), len)
+            }
+            CType::Function { returns, params } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|param| {
+                        format!(
+                            "{}: {}",
+                            param
+                                .name
+                                .as_deref()
+                                .map(rust_identifier)
+                                .unwrap_or_else(|| "_".to_owned()),
+                            param.ty.clone().decay().to_rust(opts, resolve)
+                        )
+                    })
+                    .collect();
+                let mut ret = format!(
+                    "extern \"{}\" fn({})",
+                    opts.abi,
+                    params.join(", ")
+                );
+                if !returns.is_void() {
+                    ret += " -> ";
+                    ret += &returns.to_rust(opts, resolve);
+                }
+                ret
+            }
+        }
+    }
+}
+
+/// The Rust name of a named type that isn't a builtin, e.g. `_cl_event` for
+/// `struct _cl_event`.
+pub fn rust_type_name(name: &str) -> String {
+    rust_identifier(name.strip_prefix("struct ").unwrap_or(name))
+}
+
+/// Turns a C identifier into a Rust one, escaping any that are Rust
+/// keywords.
+pub fn rust_identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const",
+        "continue", "do", "dyn", "else", "enum", "extern", "false", "final",
+        "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+        "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type",
+        "typeof", "unsafe", "unsized", "use", "virtual", "where", "while",
+        "yield",
+    ];
+    match name {
+        // these can't be raw identifiers
+        "self" | "Self" | "super" | "crate" | "_" => format!("{}_", name),
+        x if KEYWORDS.contains(&x) => format!("r#{}", x),
+        x => x.to_owned(),
+    }
+}
+
+/// The Rust equivalent of a builtin C type (or one from `stdint.h` or
+/// `khrplatform.h`), if it is one.
+pub fn builtin_type(name: &str, opts: &CmdLine) -> Option<&'static str> {
+    lazy_static! {
+        static ref STATIC_TYPES_LIBC: HashMap<&'static str, &'static str>
+            = [
+                // We can't count on these to map to particular Rust types for
+                // the most part. For most PC/mobile platforms it would be
+                // acceptable to do so, but I intend to support even
+                // Deathstation 9000s with this library. So... use libc types.
+                ("void", "libc::c_void"),
+                ("char", "libc::c_char"),
+                ("unsigned char", "libc::c_uchar"),
+                ("signed char", "libc::c_schar"),
+                ("short", "libc::c_short"),
+                ("unsigned short", "libc::c_ushort"),
+                ("int", "libc::c_int"),
+                ("unsigned int", "libc::c_uint"),
+                ("long", "libc::c_long"),
+                ("unsigned long", "libc::c_ulong"),
+                ("float", "libc::c_float"),
+                ("double", "libc::c_double"),
+                // C99-ish types
+                ("ptrdiff_t", "libc::ptrdiff_t"),
+                ("intptr_t", "libc::intptr_t"),
+                ("size_t", "libc::size_t"),
+                ("ssize_t", "libc::ssize_t"),
+                ("int8_t", "libc::int8_t"),
+                ("int16_t", "libc::int16_t"),
+                ("int32_t", "libc::int32_t"),
+                ("int64_t", "libc::int64_t"),
+                ("uint8_t", "libc::uint8_t"),
+                ("uint16_t", "libc::uint16_t"),
+                ("uint32_t", "libc::uint32_t"),
+                ("uint64_t", "libc::uint64_t"),
+                // "khrplatform.h" types... sigh...
+                ("khronos_ptrdiff_t", "isize"),
+                ("khronos_intptr_t", "usize"),
+                ("khronos_size_t", "usize"),
+                ("khronos_ssize_t", "isize"),
+                ("khronos_int8_t", "i8"),
+                ("khronos_int16_t", "i16"),
+                ("khronos_int32_t", "i32"),
+                ("khronos_int64_t", "i64"),
+                ("khronos_uint8_t", "u8"),
+                ("khronos_uint16_t", "u16"),
+                ("khronos_uint32_t", "u32"),
+                ("khronos_uint64_t", "u64"),
+                ("khronos_float_t", "f32"),
+                ("khronos_double_t", "f64"),
+            ].into_iter().collect();
+        static ref STATIC_TYPES_NO_LIBC: HashMap<&'static str, &'static str>
+            = [
+                ("void", "()"),
+                ("char", "u8"),
+                ("unsigned char", "u8"),
+                ("signed char", "i8"),
+                ("short", "i16"),
+                ("unsigned short", "u16"),
+                ("int", "i32"),
+                ("unsigned int", "u32"),
+                ("long", "i32"),
+                ("unsigned long", "u32"),
+                ("float", "f32"),
+                ("double", "f64"),
+                // C99-ish types
+                ("ptrdiff_t", "isize"),
+                ("intptr_t", "usize"),
+                ("size_t", "usize"),
+                ("ssize_t", "isize"),
+                ("int8_t", "i8"),
+                ("int16_t", "i16"),
+                ("int32_t", "i32"),
+                ("int64_t", "i64"),
+                ("uint8_t", "u8"),
+                ("uint16_t", "u16"),
+                ("uint32_t", "u32"),
+                ("uint64_t", "u64"),
+                // "khrplatform.h" types... sigh...
+                ("khronos_ptrdiff_t", "isize"),
+                ("khronos_intptr_t", "usize"),
+                ("khronos_size_t", "usize"),
+                ("khronos_ssize_t", "isize"),
+                ("khronos_int8_t", "i8"),
+                ("khronos_int16_t", "i16"),
+                ("khronos_int32_t", "i32"),
+                ("khronos_int64_t", "i64"),
+                ("khronos_uint8_t", "u8"),
+                ("khronos_uint16_t", "u16"),
+                ("khronos_uint32_t", "u32"),
+                ("khronos_uint64_t", "u64"),
+                ("khronos_float_t", "f32"),
+                ("khronos_double_t", "f64"),
+            ].into_iter().collect();
+    }
+    let static_types = if opts.use_libc {
+        &*STATIC_TYPES_LIBC
+    } else {
+        &*STATIC_TYPES_NO_LIBC
+    };
+    static_types.get(name).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versions::parse_version;
+
+    fn opts() -> CmdLine {
+        CmdLine {
+            version: parse_version("gl3.3").unwrap(),
+            xml_path: None,
+            extensions: Vec::new(),
+            use_libc: true,
+            used_identifiers_path: None,
+            group_enums: false,
+            abi: "system".to_owned(),
+            no_std: false,
+            disabled_extensions_var: None,
+            global: false,
+            typed_handles: false,
+            object_wrappers: false,
+            snake_case: false,
+            constants_module: None,
+            driver_info: false,
+            registry_cache: None,
+        }
+    }
+
+    fn named(name: &str, is_const: bool) -> CType {
+        CType::Named {
+            name: name.to_owned(),
+            is_const,
+        }
+    }
+
+    fn pointer(to: CType, is_const: bool) -> CType {
+        CType::Pointer {
+            to: Box::new(to),
+            is_const,
+        }
+    }
+
+    fn parse_type(text: &str) -> CType {
+        parse_declaration(text).unwrap().ty
+    }
+
+    fn rust(ty: &CType) -> String {
+        ty.to_rust(&opts(), &mut |x| rust_type_name(x))
+    }
+
+    #[test]
+    fn const_on_either_side_of_star() {
+        let const_target = pointer(named("GLuint", true), false);
+        assert_eq!(parse_type("const GLuint *ids"), const_target);
+        assert_eq!(parse_type("GLuint const *ids"), const_target);
+        assert_eq!(rust(&const_target), "*const GLuint");
+        let const_pointer = pointer(named("GLuint", false), true);
+        assert_eq!(parse_type("GLuint *const ids"), const_pointer);
+        assert_eq!(rust(&const_pointer), "*mut GLuint");
+    }
+
+    #[test]
+    fn const_pointer_to_const_pointer() {
+        let declaration =
+            parse_declaration("const GLchar *const*string").unwrap();
+        assert_eq!(declaration.name.as_deref(), Some("string"));
+        assert_eq!(
+            declaration.ty,
+            pointer(pointer(named("GLchar", true), true), false)
+        );
+        assert_eq!(rust(&declaration.ty), "*const *const GLchar");
+    }
+
+    #[test]
+    fn multidimensional_arrays() {
+        let ty = parse_type("GLfloat m[2][3]");
+        assert_eq!(
+            ty,
+            CType::Array {
+                of: Box::new(CType::Array {
+                    of: Box::new(named("GLfloat", false)),
+                    len: 3,
+                }),
+                len: 2,
+            }
+        );
+        assert_eq!(rust(&ty), "[[GLfloat; 3]; 2]");
+        // only the outermost array decays
+        assert_eq!(rust(&ty.decay()), "*mut [GLfloat; 3]");
+        assert_eq!(
+            rust(&parse_type("const GLfloat m[2][3]").decay()),
+            "*const [GLfloat; 3]"
+        );
+    }
+
+    #[test]
+    fn debug_callback_typedef() {
+        let text = "typedef void (APIENTRY *GLDEBUGPROC)(GLenum source, \
+                    GLenum type, GLuint id, GLenum severity, GLsizei length, \
+                    const GLchar *message, const void *userParam);";
+        // (the typedef is the caller's business)
+        assert!(parse_declaration(text).is_err());
+        let text = text.strip_prefix("typedef ").unwrap();
+        let expected = "Option<extern \"system\" fn(source: GLenum, \
+                        r#type: GLenum, id: GLuint, severity: GLenum, \
+                        length: GLsizei, message: *const GLchar, \
+                        userParam: *const libc::c_void)>";
+        let declaration = parse_declaration(text).unwrap();
+        assert_eq!(declaration.name.as_deref(), Some("GLDEBUGPROC"));
+        assert_eq!(rust(&declaration.ty), expected);
+        // gl.xml spells `APIENTRY` as `<apientry/>`, which has no text
+        let declaration =
+            parse_declaration(&text.replace("APIENTRY ", "")).unwrap();
+        assert_eq!(declaration.name.as_deref(), Some("GLDEBUGPROC"));
+        assert_eq!(rust(&declaration.ty), expected);
+    }
+
+    #[test]
+    fn void_parameter_lists() {
+        let ty = parse_type("GLenum glGetError(void)");
+        assert_eq!(
+            ty,
+            CType::Function {
+                returns: Box::new(named("GLenum", false)),
+                params: Vec::new(),
+            }
+        );
+        assert_eq!(
+            rust(&parse_type("void (*callback)(void)")),
+            "Option<extern \"system\" fn()>"
+        );
+        // `void *` is a parameter, not an empty list
+        assert_eq!(
+            rust(&parse_type("void (*callback)(void *)")),
+            "Option<extern \"system\" fn(_: *mut libc::c_void)>"
+        );
+    }
+
+    #[test]
+    fn builtin_types() {
+        assert_eq!(rust(&parse_type("int unsigned x")), "libc::c_uint");
+        assert_eq!(rust(&parse_type("unsigned x")), "libc::c_uint");
+        assert_eq!(rust(&parse_type("signed char x")), "libc::c_schar");
+        assert_eq!(rust(&parse_type("struct _cl_event *x")), "*mut _cl_event");
+    }
+
+    #[test]
+    fn keyword_escaping() {
+        assert_eq!(rust_identifier("type"), "r#type");
+        assert_eq!(rust_identifier("ref"), "r#ref");
+        assert_eq!(rust_identifier("gen"), "r#gen");
+        assert_eq!(rust_identifier("self"), "self_");
+        assert_eq!(rust_identifier("Self"), "Self_");
+        assert_eq!(rust_identifier("_"), "__");
+        assert_eq!(rust_identifier("target"), "target");
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        for (text, why) in [
+            ("GLuint &ids", "unsupported character `&`"),
+            ("GLuint ids[0x1g]", "bad number `0x1g`"),
+            ("volatile GLuint x", "`volatile` isn't supported"),
+            ("GLuint *restrict x", "`restrict` isn't supported"),
+            ("typedef GLuint x", "`typedef` isn't supported"),
+            ("GLuint int", "misplaced `int`"),
+            ("unsigned struct x", "misplaced `struct`"),
+            ("struct *x", "expected a name, found `*`"),
+            ("", "expected a type, found the end"),
+            ("GLuint ids[n]", "expected an array length, found `n`"),
+            ("GLuint ids[4", "expected `]`, found the end"),
+            ("void f()", "parameter lists must say `void`, not be empty"),
+            ("void f(GLuint x", "expected `)`, found the end"),
+            ("GLuint x y", "expected the end, found `y`"),
+        ] {
+            assert_eq!(
+                parse_declaration(text),
+                Err(why.to_owned()),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::BTreeMap, io::Write};
+
+use crate::{
+    cmdline::CmdLine, resolve_binding, used_identifiers::*, Registry,
+};
+
+/// Every identifier of one kind in a binding, in order, mapped to the
+/// extension that provides it (`""` if none is needed) and its definition.
+type Listing<'a> = BTreeMap<&'a str, (&'a str, String)>;
+
+/// Lists the types, values and commands that would go into a binding.
+fn list_binding<'a>(
+    opts: &'a CmdLine,
+    registry: &'a Registry,
+) -> [Listing<'a>; 3] {
+    let mut used_identifier_set = get_used_identifiers(opts);
+    let [type_set, value_set, command_exts] =
+        resolve_binding(opts, registry, &mut used_identifier_set);
+    let mut types = Listing::new();
+    // Types needed by several commands are attributed to whichever one
+    // reached them first, so don't compare which extension provides them.
+    for typ in type_set.into_keys() {
+        if let Some(definition) = registry.type_map.get(typ) {
+            let mut code = Vec::new();
+            definition.output(opts, &mut code);
+            // (types that come from platform headers have no definition)
+            if !code.is_empty() {
+                let code = String::from_utf8(code).unwrap();
+                types.insert(typ, ("", code.trim_end().to_owned()));
+            }
+        }
+    }
+    let mut values = Listing::new();
+    for (value, ext) in value_set {
+        if !used_identifier_set.contains(value) {
+            continue;
+        }
+        if let Some(definition) = registry.value_map.get(value) {
+            let mut code = Vec::new();
+            definition.output(value, opts, &mut code);
+            let code = String::from_utf8(code).unwrap();
+            values.insert(value, (ext, code.trim_end().to_owned()));
+        }
+    }
+    let mut commands = Listing::new();
+    for (command, ext) in command_exts {
+        if used_identifier_set.contains(command) {
+            let signature = registry.command_map[command].signature();
+            commands.insert(command, (ext, signature));
+        }
+    }
+    [types, values, commands]
+}
+
+fn describe_provider(ext: &str) -> &str {
+    if ext.is_empty() {
+        "core"
+    } else {
+        ext
+    }
+}
+
+fn output_definition<W: Write>(mark: char, definition: &str, out: &mut W) {
+    for line in definition.lines() {
+        writeln!(out, "    {} {}", mark, line).unwrap();
+    }
+}
+
+/// Outputs the differences between two listings, returning whether there
+/// were any.
+fn diff_listing<W: Write>(
+    heading: &str,
+    old: &Listing,
+    new: &Listing,
+    out: &mut W,
+) -> bool {
+    let mut names: Vec<&str> = old.keys().chain(new.keys()).cloned().collect();
+    names.sort_unstable();
+    names.dedup();
+    let mut any = false;
+    for name in names {
+        let (old_entry, new_entry) = (old.get(name), new.get(name));
+        if old_entry == new_entry {
+            continue;
+        }
+        let (header, old_def, new_def) = match (old_entry, new_entry) {
+            (None, None) => unreachable!(),
+            (None, Some((ext, definition))) => {
+                let mut line = format!("+ {}", name);
+                if !ext.is_empty() {
+                    line += &format!(" (from {})", ext);
+                }
+                (line, None, Some(definition))
+            }
+            (Some((ext, definition)), None) => {
+                let mut line = format!("- {}", name);
+                if !ext.is_empty() {
+                    line += &format!(" (from {})", ext);
+                }
+                (line, Some(definition), None)
+            }
+            (Some((old_ext, old_def)), Some((new_ext, new_def))) => {
+                let mut line = format!("~ {}", name);
+                if old_ext != new_ext {
+                    line += &format!(
+                        " (was from {}, now from {})",
+                        describe_provider(old_ext),
+                        describe_provider(new_ext)
+                    );
+                }
+                if old_def == new_def {
+                    (line, None, None)
+                } else {
+                    (line, Some(old_def), Some(new_def))
+                }
+            }
+        };
+        if !any {
+            writeln!(out, "\n*** {} ***", heading).unwrap();
+            any = true;
+        }
+        writeln!(out, "{}", header).unwrap();
+        if let Some(old_def) = old_def {
+            output_definition('-', old_def, out);
+        }
+        if let Some(new_def) = new_def {
+            output_definition('+', new_def, out);
+        }
+    }
+    any
+}
+
+/// Outputs every type, value and command that was added, removed or
+/// changed between two bindings.
+pub fn output_diff<W: Write>(
+    old_opts: &CmdLine,
+    old_registry: &Registry,
+    new_opts: &CmdLine,
+    new_registry: &Registry,
+    out: &mut W,
+) {
+    writeln!(
+        out,
+        "--- {}, from {}\n+++ {}, from {}",
+        old_opts.version,
+        old_opts.describe_xml(),
+        new_opts.version,
+        new_opts.describe_xml()
+    )
+    .unwrap();
+    let old = list_binding(old_opts, old_registry);
+    let new = list_binding(new_opts, new_registry);
+    let mut any = false;
+    for (heading, (old, new)) in ["TYPES", "VALUES", "COMMANDS"]
+        .iter()
+        .zip(old.iter().zip(&new))
+    {
+        any |= diff_listing(heading, old, new, out);
+    }
+    if !any {
+        writeln!(out, "\nNo differences.").unwrap();
+    }
+}
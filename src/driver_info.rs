@@ -0,0 +1,129 @@
+use std::io::Write;
+
+use crate::{cmdline::CmdLine, naming::constant_path};
+
+/// The strings `Procs` keeps with `--driver-info`: the field (and accessor)
+/// name, the constant that gets it, and a description for the doc comment.
+/// The last one is left out if the binding's version has no GLSL.
+const DRIVER_STRINGS: &[(&str, &str, &str)] = &[
+    (
+        "vendor",
+        "GL_VENDOR",
+        "The company responsible for the driver",
+    ),
+    ("renderer", "GL_RENDERER", "The name of the renderer"),
+    (
+        "version",
+        "GL_VERSION",
+        "The version of the GL the driver provides",
+    ),
+    (
+        "glsl_version",
+        "GL_SHADING_LANGUAGE_VERSION",
+        "The version of GLSL the driver provides",
+    ),
+];
+
+/// Returns the strings `Procs` keeps, taking out `glsl_version` if `has_glsl`
+/// is false.
+fn driver_strings(
+    has_glsl: bool,
+) -> impl Iterator<Item = &'static (&'static str, &'static str, &'static str)>
+{
+    DRIVER_STRINGS
+        .iter()
+        .filter(move |x| has_glsl || x.0 != "glsl_version")
+}
+
+/// Returns the identifiers `Procs::new` uses to gather driver info.
+pub fn driver_info_identifiers(opts: &CmdLine) -> Vec<&'static str> {
+    let mut ret = vec!["glGetString", "GL_EXTENSIONS"];
+    ret.extend(DRIVER_STRINGS.iter().map(|x| x.1));
+    if opts.version.needs_getstringi_extensions() {
+        ret.extend(["glGetIntegerv", "glGetStringi", "GL_NUM_EXTENSIONS"]);
+    }
+    ret
+}
+
+/// Outputs the fields of `Procs` that hold the driver info.
+pub fn output_driver_info_fields<W: Write>(has_glsl: bool, out: &mut W) {
+    for (name, _, _) in driver_strings(has_glsl) {
+        writeln!(out, "    {}: String,", name).unwrap();
+    }
+    writeln!(out, "    extensions: Vec<String>,").unwrap();
+}
+
+/// Outputs the `Debug` impl for `Procs`, which shows the driver info.
+pub fn output_driver_info_debug<W: Write>(has_glsl: bool, out: &mut W) {
+    write!(
+        out,
+        r#"impl fmt::Debug for Procs {{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {{
+        f.debug_struct("Procs")
+"#
+    )
+    .unwrap();
+    for (name, _, _) in driver_strings(has_glsl) {
+        writeln!(out, "            .field(\"{0}\", &self.{0})", name).unwrap();
+    }
+    write!(
+        out,
+        r#"            .field("extensions", &self.extensions)
+            .finish()
+    }}
+}}
+"#
+    )
+    .unwrap();
+}
+
+/// Outputs the initializers of the driver info fields, in `Procs::new`.
+pub fn output_driver_info_init<W: Write>(has_glsl: bool, out: &mut W) {
+    for (name, _, _) in driver_strings(has_glsl) {
+        writeln!(out, "            {}: String::new(),", name).unwrap();
+    }
+    writeln!(out, "            extensions: Vec::new(),").unwrap();
+}
+
+/// Outputs the code in `Procs::new` that asks the driver for its strings.
+/// (The extensions are gathered along with the extension flags.)
+pub fn output_driver_info_capture<W: Write>(
+    opts: &CmdLine,
+    has_glsl: bool,
+    out: &mut W,
+) {
+    for (name, constant, _) in driver_strings(has_glsl) {
+        writeln!(
+            out,
+            "        ret.{} = ret.driver_string({});",
+            name,
+            constant_path(constant, opts)
+        )
+        .unwrap();
+    }
+}
+
+/// Outputs the accessors for the driver info, and the helper that fetches
+/// it, as methods of `Procs`.
+pub fn output_driver_info_methods<W: Write>(has_glsl: bool, out: &mut W) {
+    for (name, constant, doc) in driver_strings(has_glsl) {
+        writeln!(
+            out,
+            r#"    /// {} (`{}`), as of when this `Procs` was made.
+    pub fn {}(&self) -> &str {{ &self.{} }}"#,
+            doc, constant, name, name
+        )
+        .unwrap();
+    }
+    write!(
+        out,
+        r#"    /// Every extension the driver advertised when this `Procs` was made,
+    /// including ones this binding doesn't know about, or that were disabled.
+    pub fn extensions(&self) -> &[String] {{ &self.extensions }}
+    fn driver_string(&self, name: GLenum) -> String {{
+        String::from_utf8_lossy(self.gl_string(name)).into_owned()
+    }}
+"#
+    )
+    .unwrap();
+}
@@ -3,11 +3,27 @@ use std::{
     process::exit,
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     cmdline::CmdLine,
+    commands::Command,
     dom::{Element, Node},
+    naming::strip_vendor_suffix,
+    values::Val,
 };
 
+/// An extension whose functionality went into core in some version.
+#[derive(Serialize, Deserialize)]
+pub struct Promotion {
+    /// The version, as `(major, minor)`.
+    pub version: (u32, u32),
+    /// Each of the extension's commands, mapped to the core command that
+    /// does the same thing (often the very same command).
+    pub commands: HashMap<String, String>,
+}
+
+/// Returns how many types, values and commands were handled.
 fn process_feature<'a>(
     tag: &'a Element,
     ext: &'a str,
@@ -15,25 +31,21 @@ fn process_feature<'a>(
     type_set: &mut HashMap<&'a str, &'a str>,
     value_set: &mut HashMap<&'a str, &'a str>,
     command_set: &mut HashMap<&'a str, &'a str>,
-) {
+) -> usize {
+    let mut count = 0;
     for child in tag.get_children() {
         if let Node::Element(ref element) = child {
-            match element.get_name() {
-                "type" => {
-                    handler(type_set, ext, &element.get_attributes()["name"])
-                }
-                "enum" => {
-                    handler(value_set, ext, &element.get_attributes()["name"])
-                }
-                "command" => handler(
-                    command_set,
-                    ext,
-                    &element.get_attributes()["name"],
-                ),
-                _ => (),
-            }
+            let set = match element.get_name() {
+                "type" => &mut *type_set,
+                "enum" => &mut *value_set,
+                "command" => &mut *command_set,
+                _ => continue,
+            };
+            handler(set, ext, &element.get_attributes()["name"]);
+            count += 1;
         }
     }
+    count
 }
 
 fn gather_feature<'a>(
@@ -47,6 +59,7 @@ fn gather_feature<'a>(
         if let Node::Element(ref element) = child {
             if (element.get_name() == "remove"
                 || element.get_name() == "require")
+                && opts.version.correct_api(element)
                 && opts.version.correct_profile(element)
             {
                 process_feature(
@@ -70,6 +83,8 @@ fn gather_feature<'a>(
     }
 }
 
+/// Returns how many types, values and commands the extension requires for
+/// the targeted API.
 fn gather_extension<'a>(
     tag: &'a Element,
     name: &'a str,
@@ -77,14 +92,16 @@ fn gather_extension<'a>(
     type_set: &mut HashMap<&'a str, &'a str>,
     value_set: &mut HashMap<&'a str, &'a str>,
     command_set: &mut HashMap<&'a str, &'a str>,
-) {
+) -> usize {
+    let mut required = 0;
     for child in tag.get_children() {
         if let Node::Element(ref element) = child {
             if (element.get_name() == "remove"
                 || element.get_name() == "require")
+                && opts.version.correct_api(element)
                 && opts.version.correct_profile(element)
             {
-                process_feature(
+                let count = process_feature(
                     element,
                     name,
                     &match element.get_name() {
@@ -100,9 +117,13 @@ fn gather_extension<'a>(
                     value_set,
                     command_set,
                 );
+                if element.get_name() == "require" {
+                    required += count;
+                }
             }
         }
     }
+    required
 }
 
 pub fn gather_features<'a>(
@@ -146,7 +167,7 @@ pub fn gather_features<'a>(
                             };
                             if should_gather {
                                 found_extensions.insert(name);
-                                gather_extension(
+                                let required = gather_extension(
                                     element,
                                     name,
                                     opts,
@@ -154,6 +175,13 @@ pub fn gather_features<'a>(
                                     &mut value_set,
                                     &mut command_set,
                                 );
+                                if required == 0 {
+                                    eprintln!(
+                                        "warning: {} doesn't add anything \
+                                         to {}",
+                                        name, opts.version
+                                    );
+                                }
                             }
                         }
                     }
@@ -173,3 +201,244 @@ pub fn gather_features<'a>(
     }
     [type_set, value_set, command_set]
 }
+
+/// In a compatibility profile binding, finds the values and commands that the
+/// core profile removes, mapped to the version that removed them.
+pub fn gather_deprecations(
+    root: &Element,
+    opts: &CmdLine,
+) -> HashMap<String, String> {
+    let mut ret = HashMap::new();
+    if !opts.version.is_compatibility() {
+        return ret;
+    }
+    for child in root.get_children() {
+        if let Node::Element(ref feature) = child {
+            if feature.get_name() != "feature"
+                || !opts.version.correct_api(feature)
+                || !opts.version.correct_version(feature)
+            {
+                continue;
+            }
+            let number = &feature.get_attributes()["number"];
+            for child in feature.get_children() {
+                if let Node::Element(ref element) = child {
+                    if element.get_name() != "remove"
+                        || !opts.version.correct_api(element)
+                        || element
+                            .get_attributes()
+                            .get("profile")
+                            .map(|x| x.as_str())
+                            != Some("core")
+                    {
+                        continue;
+                    }
+                    for child in element.get_children() {
+                        if let Node::Element(ref element) = child {
+                            if element.get_name() == "enum"
+                                || element.get_name() == "command"
+                            {
+                                ret.entry(
+                                    element.get_attributes()["name"].clone(),
+                                )
+                                .or_insert_with(|| number.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Finds which of the requested extensions went into core, and in which
+/// version. An extension counts once all of its commands are in core,
+/// themselves or as the command they are an alias of, and all of its values
+/// are in core, themselves or under the same name minus the vendor suffix
+/// (with the same value).
+pub fn gather_promotions(
+    root: &Element,
+    opts: &CmdLine,
+    value_map: &HashMap<String, Val>,
+    command_map: &HashMap<String, Command>,
+) -> HashMap<String, Promotion> {
+    let mut features = Vec::new();
+    // (extension, values, commands)
+    let mut requirements = Vec::new();
+    for child in root.get_children() {
+        if let Node::Element(ref element) = child {
+            if element.get_name() == "feature"
+                && opts.version.correct_api(element)
+            {
+                let number = &element.get_attributes()["number"];
+                let (major, minor) = number.split_once('.').unwrap();
+                let version: (u32, u32) =
+                    (major.parse().unwrap(), minor.parse().unwrap());
+                features.push((version, element));
+            } else if element.get_name() == "extensions" {
+                for child in element.get_children() {
+                    if let Node::Element(ref element) = child {
+                        let name = &element.get_attributes()["name"];
+                        if element.get_name() != "extension"
+                            || !opts.extensions.contains(name)
+                        {
+                            continue;
+                        }
+                        let mut values = Vec::new();
+                        let mut commands = Vec::new();
+                        for child in element.get_children() {
+                            if let Node::Element(ref element) = child {
+                                if element.get_name() == "require"
+                                    && opts.version.correct_api(element)
+                                    && opts.version.correct_profile(element)
+                                {
+                                    gather_requirements(
+                                        element,
+                                        &mut values,
+                                        &mut commands,
+                                    );
+                                }
+                            }
+                        }
+                        if !values.is_empty() || !commands.is_empty() {
+                            requirements.push((name, values, commands));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    features.sort_by_key(|x| x.0);
+    let mut core_values = HashSet::new();
+    let mut core_commands = HashSet::new();
+    let mut ret = HashMap::new();
+    for (version, feature) in features {
+        for child in feature.get_children() {
+            if let Node::Element(ref element) = child {
+                if !opts.version.correct_api(element)
+                    || !opts.version.correct_profile(element)
+                {
+                    continue;
+                }
+                let (mut values, mut commands) = (Vec::new(), Vec::new());
+                gather_requirements(element, &mut values, &mut commands);
+                match element.get_name() {
+                    "require" => {
+                        core_values.extend(values);
+                        core_commands.extend(commands);
+                    }
+                    "remove" => {
+                        for value in values {
+                            core_values.remove(value);
+                        }
+                        for command in commands {
+                            core_commands.remove(command);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        for (ext, values, commands) in &requirements {
+            if ret.contains_key(ext.as_str()) {
+                continue;
+            }
+            let values_in_core = values.iter().all(|value| {
+                core_values.contains(value)
+                    || strip_vendor_suffix(value, ext)
+                        .filter(|x| core_values.contains(x))
+                        .map(|x| {
+                            value_map.get(x).is_some()
+                                && value_map.get(x) == value_map.get(*value)
+                        })
+                        .unwrap_or(false)
+            });
+            if !values_in_core {
+                continue;
+            }
+            let core_names: Option<HashMap<String, String>> = commands
+                .iter()
+                .map(|&command| {
+                    let core_name = if core_commands.contains(command) {
+                        command
+                    } else {
+                        command_map
+                            .get(command)?
+                            .get_alias()
+                            .filter(|x| core_commands.contains(x))?
+                    };
+                    Some((command.to_owned(), core_name.to_owned()))
+                })
+                .collect();
+            if let Some(commands) = core_names {
+                ret.insert(ext.to_string(), Promotion { version, commands });
+            }
+        }
+    }
+    ret
+}
+
+/// Adds the names of the values and commands in a `<require>` or `<remove>`
+/// to the given lists.
+fn gather_requirements<'a>(
+    tag: &'a Element,
+    values: &mut Vec<&'a str>,
+    commands: &mut Vec<&'a str>,
+) {
+    for child in tag.get_children() {
+        if let Node::Element(ref element) = child {
+            let list = match element.get_name() {
+                "enum" => &mut *values,
+                "command" => &mut *commands,
+                _ => continue,
+            };
+            list.push(&element.get_attributes()["name"]);
+        }
+    }
+}
+
+/// When generating several bindings at once, each extension named on the
+/// command line goes into every binding whose API supports it. It's an error
+/// if none of them do. (Extensions that don't exist at all are left for
+/// `gather_features` to complain about.)
+pub fn distribute_extensions(root: &Element, targets: &mut [CmdLine]) {
+    let requested = targets[0].extensions.clone();
+    for target in targets.iter_mut() {
+        target.extensions.clear();
+    }
+    let mut errors_have_happened = false;
+    for ext in requested {
+        let mut element = None;
+        for child in root.get_children() {
+            if let Node::Element(ref child) = child {
+                if child.get_name() == "extensions" {
+                    for child in child.get_children() {
+                        if let Node::Element(ref child) = child {
+                            if child.get_name() == "extension"
+                                && child.get_attributes()["name"] == ext
+                            {
+                                element = Some(child);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let mut supported_anywhere = false;
+        for target in targets.iter_mut() {
+            if element.map(|x| target.version.supported(x)).unwrap_or(true) {
+                supported_anywhere = true;
+                target.extensions.push(ext.clone());
+            }
+        }
+        if !supported_anywhere {
+            errors_have_happened = true;
+            eprintln!("{} is not supported by any of the selected APIs", ext);
+        }
+    }
+    if errors_have_happened {
+        eprintln!("Errors have occurred, panicking");
+        exit(1);
+    }
+}
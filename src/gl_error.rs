@@ -0,0 +1,144 @@
+use std::{collections::HashMap, io::Write};
+
+use crate::{
+    cmdline::CmdLine, commands::Command, groups::Group,
+    naming::strip_vendor_suffix, values::Val,
+};
+
+/// Turns `GL_INVALID_FRAMEBUFFER_OPERATION` into
+/// `InvalidFramebufferOperation`. `ext` is the extension that provides the
+/// constant (`""` for core), whose vendor suffix is dropped.
+fn variant_name(name: &str, ext: &str) -> String {
+    let name = name.strip_prefix("GL_").unwrap_or(name);
+    let name = strip_vendor_suffix(name, ext).unwrap_or(name);
+    let mut ret = String::new();
+    for word in name.split('_') {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            ret.push(first);
+            ret.extend(chars.map(|x| x.to_ascii_lowercase()));
+        }
+    }
+    ret
+}
+
+/// Outputs `GlError`, with a variant for every error in the `ErrorCode` group
+/// that the binding's version and extensions provide, and
+/// `Procs::check_error`.
+pub fn output_gl_error<W: Write>(
+    opts: &CmdLine,
+    get_error: &Command,
+    value_map: &HashMap<String, Val>,
+    value_set: &HashMap<&str, &str>,
+    group_map: &HashMap<String, Group>,
+    out: &mut W,
+) {
+    let mut errors: Vec<(u32, bool, &str, &str)> = Vec::new();
+    if let Some(group) = group_map.get("ErrorCode") {
+        for name in group.get_elements() {
+            if let (Some(ext), Some(&Val::U32(value))) =
+                (value_set.get(name.as_str()), value_map.get(name))
+            {
+                if value != 0 {
+                    errors.push((value, !ext.is_empty(), name, ext));
+                }
+            }
+        }
+    }
+    // (core names beat extension ones, as in `enum_name`)
+    errors.sort_unstable();
+    errors.dedup_by_key(|x| x.0);
+    writeln!(
+        out,
+        r#"
+/// An error reported by `glGetError`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlError {{"#
+    )
+    .unwrap();
+    for &(_, _, name, ext) in &errors {
+        writeln!(out, "    /// `{}`", name).unwrap();
+        writeln!(out, "    {},", variant_name(name, ext)).unwrap();
+    }
+    write!(
+        out,
+        r#"    /// An error this binding doesn't know about.
+    Unknown(GLenum),
+}}
+
+impl GlError {{
+    pub fn from_raw(raw: GLenum) -> Self {{
+        match raw {{
+"#
+    )
+    .unwrap();
+    for &(value, _, name, ext) in &errors {
+        writeln!(
+            out,
+            "            0x{:04X} => GlError::{},",
+            value,
+            variant_name(name, ext)
+        )
+        .unwrap();
+    }
+    write!(
+        out,
+        r#"            raw => GlError::Unknown(raw),
+        }}
+    }}
+}}
+
+impl fmt::Display for GlError {{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {{
+        match *self {{
+"#
+    )
+    .unwrap();
+    for &(_, _, name, ext) in &errors {
+        writeln!(
+            out,
+            "            GlError::{} => f.write_str(\"{}\"),",
+            variant_name(name, ext),
+            name
+        )
+        .unwrap();
+    }
+    write!(
+        out,
+        r#"            GlError::Unknown(raw) => write!(f, "unknown GL error 0x{{:04X}}", raw),
+        }}
+    }}
+}}
+"#
+    )
+    .unwrap();
+    // `core::error::Error` is too new to rely on
+    if !opts.no_std {
+        writeln!(out, "\nimpl std::error::Error for GlError {{}}").unwrap();
+    }
+    write!(
+        out,
+        r#"
+impl Procs {{
+    /// Calls `glGetError` until it reports no more errors, and returns the
+    /// first one it reported, if any. (Some drivers report an error forever
+    /// once the context is lost, so this gives up after a while.)
+    pub unsafe fn check_error(&self) -> Result<(), GlError> {{
+        let mut ret = Ok(());
+        for _ in 0..32 {{
+            let raw = unsafe {{ self.{get_error}() }};
+            if raw == 0 {{
+                break;
+            }}
+            if ret.is_ok() {{
+                ret = Err(GlError::from_raw(raw));
+            }}
+        }}
+        ret
+    }}
+}}
+"#,
+        get_error = get_error.method_name(),
+    )
+    .unwrap();
+}
@@ -1,23 +1,31 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     cmdline::CmdLine,
     dom::{Element, Node},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 enum GroupType {
     Bitmask,
     Ordinary,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Group {
     elements: Vec<String>,
     comment: Option<String>,
     group_type: Option<GroupType>,
 }
 
+impl Group {
+    pub fn get_elements(&self) -> &[String] {
+        &self.elements
+    }
+}
+
 fn new_group() -> Group {
     Group {
         elements: Vec::new(),
@@ -96,6 +104,28 @@ pub fn gather_groups(
                     // grumble grumble...
                     map.insert(group_name.clone(), group);
                 }
+                // Newer registries list group membership on the individual
+                // enums instead.
+                for child in element.get_children() {
+                    if let Node::Element(ref element) = child {
+                        if element.get_name() != "enum" {
+                            continue;
+                        }
+                        let attributes = element.get_attributes();
+                        if let (Some(enum_name), Some(group_names)) =
+                            (attributes.get("name"), attributes.get("group"))
+                        {
+                            for group_name in group_names.split(',') {
+                                let group = map
+                                    .entry(group_name.to_owned())
+                                    .or_insert_with(new_group);
+                                if !group.elements.contains(enum_name) {
+                                    group.elements.push(enum_name.clone());
+                                }
+                            }
+                        }
+                    }
+                }
             }
         }
     }
@@ -1,7 +1,5 @@
 // Yikes!
 
-use std::io::BufRead;
-
 mod dom;
 
 mod versions;
@@ -9,9 +7,20 @@ mod versions;
 mod cmdline;
 use cmdline::*;
 
+mod config;
+
+mod cache;
+
+#[cfg(feature = "bundled-registry")]
+mod bundled;
+
+mod diff;
+
 mod comments;
 use comments::*;
 
+mod declarator;
+
 mod types;
 use types::*;
 
@@ -30,12 +39,30 @@ use features::*;
 mod used_identifiers;
 use used_identifiers::*;
 
+mod debug;
+use debug::*;
+
+mod gl_error;
+use gl_error::*;
+
+mod driver_info;
+use driver_info::*;
+
+mod naming;
+use naming::*;
+
+mod objects;
+use objects::*;
+
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::process::exit;
 
+use serde::{Deserialize, Serialize};
+
 /// Sorts the commands such that each required extension corresponds to a
 /// contiguous range of procs.
 #[allow(clippy::type_complexity)]
@@ -95,127 +122,372 @@ fn sort_commands<'a>(
     (sorted, indices, ranges)
 }
 
-fn main() {
-    let opts = match parse_cmdline() {
-        None => exit(1),
-        Some(opts) => opts,
-    };
-    let used_identifier_set = get_used_identifiers(&opts);
-    let mut file = io::BufReader::new(fs::File::open(&opts.xml_path).unwrap());
-    // skip a byte order mark if there is one
-    {
-        let top = file.fill_buf().unwrap();
-        if top.starts_with(b"\xEF\xBB\xBF") {
-            file.consume(3);
+/// Everything from the registry that one binding might need.
+#[derive(Serialize, Deserialize)]
+struct Registry {
+    type_map: HashMap<String, Type>,
+    type_order: Vec<String>,
+    group_map: HashMap<String, Group>,
+    value_map: HashMap<String, Val>,
+    value_order: Vec<String>,
+    command_map: HashMap<String, Command>,
+    command_order: Vec<String>,
+    /// Handle type names, mapped to the types they wrap.
+    handle_map: HashMap<String, String>,
+    /// Values and commands that the core profile removes, mapped to the
+    /// version that removed them. Always empty unless this is a
+    /// compatibility profile binding.
+    deprecated: HashMap<String, String>,
+    /// The requested extensions that went into core in some version.
+    promoted: HashMap<String, Promotion>,
+    /// The types, values and commands the binding's version and extensions
+    /// provide, as `gather_features` returns them.
+    features: [HashMap<String, String>; 3],
+}
+
+fn gather_registry(xml: &dom::Element, opts: &CmdLine) -> Registry {
+    let (type_map, type_order) = gather_types(xml, opts);
+    let (group_map, _group_order) = gather_groups(xml, opts);
+    let (value_map, value_order) = gather_values(xml, opts);
+    let features = gather_features(xml, opts);
+    let (command_map, command_order, handle_map) =
+        gather_commands(xml, opts, &features[2]);
+    let promoted = gather_promotions(xml, opts, &value_map, &command_map);
+    Registry {
+        type_map,
+        type_order,
+        group_map,
+        value_map,
+        value_order,
+        command_map,
+        command_order,
+        handle_map,
+        deprecated: gather_deprecations(xml, opts),
+        promoted,
+        features: features.map(|set| {
+            set.into_iter()
+                .map(|(name, ext)| (name.to_owned(), ext.to_owned()))
+                .collect()
+        }),
+    }
+}
+
+/// Works out which types, values and commands go into a binding (mapped to
+/// the extension that provides them, or `""` if none is needed). Adds any
+/// identifiers that the used ones depend on to the used identifier set.
+fn resolve_binding<'a>(
+    opts: &'a CmdLine,
+    registry: &'a Registry,
+    used_identifier_set: &mut UsedIdentifiers,
+) -> [HashMap<&'a str, &'a str>; 3] {
+    let [mut type_set, value_set, command_exts] =
+        registry.features.each_ref().map(|set| {
+            set.iter()
+                .map(|(name, ext)| (name.as_str(), ext.as_str()))
+                .collect::<HashMap<_, _>>()
+        });
+    if opts.group_enums {
+        let mut group_enums = Vec::new();
+        for command in command_exts.keys() {
+            if used_identifier_set.contains(command) {
+                for group in registry.command_map[*command].get_groups() {
+                    if let Some(group) = registry.group_map.get(group) {
+                        group_enums.extend(group.get_elements());
+                    }
+                }
+            }
+        }
+        for value in group_enums {
+            used_identifier_set.insert(value);
+        }
+    }
+    // (`Procs::new` needs these to tell whether promoted extensions are in
+    // core)
+    if !registry.promoted.is_empty() {
+        used_identifier_set.insert("glGetString");
+        used_identifier_set.insert("GL_VERSION");
+    }
+    if opts.driver_info {
+        for identifier in driver_info_identifiers(opts) {
+            used_identifier_set.insert(identifier);
         }
     }
-    let xml = dom::read_xml(file);
-    assert!(xml.get_name() == "registry");
-    let (type_map, type_order) = gather_types(&xml, &opts);
-    let (_group_map, _group_order) = gather_groups(&xml, &opts);
-    let (value_map, value_order) = gather_values(&xml, &opts);
-    let (command_map, command_order) = gather_commands(&xml, &opts);
-    let [mut type_set, value_set, command_exts] = gather_features(&xml, &opts);
     for (command, ext) in &command_exts {
         if used_identifier_set.contains(command) {
-            let command = &command_map[*command];
+            let command = &registry.command_map[*command];
             command.touch_types(&mut type_set, ext);
         }
     }
-    print!(
-        r"#![allow(dead_code,non_snake_case,non_upper_case_globals,unused_imports,clippy::all)]
+    close_type_set(&registry.type_map, &mut type_set);
+    [type_set, value_set, command_exts]
+}
 
-//! This module was generated using the rglgen crate.
-//! It is a {}binding for {}.
-",
+/// Works out which types can go into the shared `types` module when
+/// generating several bindings at once: those that are defined identically
+/// in every binding that uses them, and only depend on other shared types.
+/// Returns them in output order.
+fn find_shared_types<'a>(
+    registries: &'a [Registry],
+    features: &[[HashMap<&'a str, &'a str>; 3]],
+) -> Vec<&'a str> {
+    let mut candidates: HashMap<&str, &Type> = HashMap::new();
+    let mut order = Vec::new();
+    let mut rejected = HashSet::new();
+    for (registry, [type_set, _, _]) in registries.iter().zip(features) {
+        for typ in &registry.type_order {
+            if !type_set.contains_key(typ.as_str()) {
+                continue;
+            }
+            let definition = &registry.type_map[typ];
+            match candidates.get(typ.as_str()) {
+                None => {
+                    candidates.insert(typ, definition);
+                    order.push(typ.as_str());
+                }
+                Some(other) if *other != definition => {
+                    rejected.insert(typ.as_str());
+                }
+                Some(_) => (),
+            }
+        }
+    }
+    loop {
+        let newly_rejected: Vec<&str> = candidates
+            .iter()
+            .filter(|(typ, definition)| {
+                !rejected.contains(*typ)
+                    && definition
+                        .get_requires()
+                        .iter()
+                        .any(|req| rejected.contains(req.as_str()))
+            })
+            .map(|(typ, _)| *typ)
+            .collect();
+        if newly_rejected.is_empty() {
+            break;
+        }
+        rejected.extend(newly_rejected);
+    }
+    order.retain(|typ| !rejected.contains(typ));
+    order
+}
+
+fn output_binding_doc<W: Write>(opts: &CmdLine, lead: &str, out: &mut W) {
+    writeln!(
+        out,
+        "//! {} a {}binding for {}.",
+        lead,
         match opts.used_identifiers_path {
             Some(_) => "partial ",
             _ => "",
         },
         opts.version
-    );
+    )
+    .unwrap();
     if !opts.extensions.is_empty() {
-        print!(
+        write!(
+            out,
             r"//!
 //! It includes support for the following extensions:
 "
-        );
+        )
+        .unwrap();
         for ext in &opts.extensions {
-            println!("//! - {}", ext);
+            writeln!(out, "//! - {}", ext).unwrap();
         }
     } else {
-        println!(r"//! It does not support any extensions.");
+        writeln!(out, r"//! It does not support any extensions.").unwrap();
     }
-    print!(
+}
+
+fn output_xml_comments<W: Write>(comments: &str, out: &mut W) {
+    write!(
+        out,
         "
 // The following comments are from the source XML file. It refers to that file,
 // not this generated Rust code. Nevertheless, valuable copyright and
 // provenance data may be present.
 "
-    );
-    output_comment_elements(&xml);
-    println!("\n// *** TYPES ***");
+    )
+    .unwrap();
+    output_comments(comments, out);
+}
+
+/// Outputs a `#[deprecated]` attribute for a value or command, if the core
+/// profile removed it.
+fn output_deprecation<W: Write>(
+    deprecated: &HashMap<String, String>,
+    name: &str,
+    indent: &str,
+    out: &mut W,
+) {
+    if let Some(version) = deprecated.get(name) {
+        writeln!(
+            out,
+            "{}#[deprecated(note = \"removed from core profile in {}\")]",
+            indent, version
+        )
+        .unwrap();
+    }
+}
+
+/// Outputs everything in a binding except its documentation, leaving out any
+/// types in `shared_types`.
+fn output_binding<W: Write>(
+    opts: &CmdLine,
+    registry: &Registry,
+    features: [HashMap<&str, &str>; 3],
+    used_identifier_set: &UsedIdentifiers,
+    shared_types: &HashSet<&str>,
+    out: &mut W,
+) {
+    let Registry {
+        type_map,
+        type_order,
+        value_map,
+        value_order,
+        group_map,
+        command_map,
+        command_order,
+        handle_map,
+        deprecated,
+        promoted,
+        ..
+    } = registry;
+    let [type_set, value_set, command_exts] = features;
+    writeln!(out, "\n// *** TYPES ***").unwrap();
     // no longer helpful in Rust 2018
     /*
     if opts.use_libc {
-        println!("use libc;");
+        writeln!(out, "use libc;").unwrap();
     }
     */
-    for typ in &type_order {
-        if type_set.contains_key(typ.as_str()) {
-            type_map[typ].output(&opts);
+    for typ in type_order {
+        if type_set.contains_key(typ.as_str())
+            && !shared_types.contains(typ.as_str())
+        {
+            type_map[typ].output(opts, out);
         }
     }
-    println!("\n// *** VALUES ***");
-    for value in &value_order {
-        if used_identifier_set.contains(value.as_str())
-            && value_set.contains_key(value.as_str())
-        {
-            value_map[value].output(value, &opts);
+    let mut handles: Vec<&str> = command_exts
+        .keys()
+        .filter(|command| used_identifier_set.contains(command))
+        .flat_map(|command| command_map[*command].get_handles())
+        .map(|handle| handle.as_str())
+        .collect();
+    handles.sort_unstable();
+    handles.dedup();
+    for handle in handles {
+        writeln!(out,
+            "#[repr(transparent)] #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)] pub struct {}(pub {});",
+            handle,
+            handle_map[handle]
+        ).unwrap();
+    }
+    writeln!(out, "\n// *** VALUES ***").unwrap();
+    if let Some(ref module) = opts.constants_module {
+        writeln!(
+            out,
+            "pub mod {} {{\n//! GL constants, without their `GL_` prefix.",
+            module
+        )
+        .unwrap();
+    }
+    let mut emitted_values = Vec::new();
+    for value in value_order {
+        if used_identifier_set.contains(value.as_str()) {
+            if let Some(ext) = value_set.get(value.as_str()) {
+                output_deprecation(deprecated, value, "", out);
+                value_map[value].output(value, opts, out);
+                emitted_values.push((value.as_str(), *ext));
+            }
         }
     }
-    println!("\n// *** COMMANDS ***\npub struct Procs {{");
+    if opts.constants_module.is_some() {
+        writeln!(out, "}}").unwrap();
+        check_collisions(
+            emitted_values
+                .iter()
+                .map(|&(name, _)| (constant_name(name, opts), name)),
+        );
+    }
+    output_enum_names(&emitted_values, value_map, group_map, out);
+    writeln!(out, "\n// *** COMMANDS ***\npub struct Procs {{").unwrap();
 
     let (sorted_commands, proc_indices, ext_proc_ranges) = sort_commands(
-        &used_identifier_set,
-        &command_map,
+        used_identifier_set,
+        command_map,
         &command_exts,
-        &command_order,
+        command_order,
     );
 
-    println!("    procs: [*const (); {}],", sorted_commands.len());
+    writeln!(out, "    procs: [*const (); {}],", sorted_commands.len())
+        .unwrap();
+    let used_commands: Vec<&str> = command_order
+        .iter()
+        .map(|x| x.as_str())
+        .filter(|x| {
+            used_identifier_set.contains(x) && command_exts.contains_key(x)
+        })
+        .collect();
+    check_collisions(
+        RESERVED_METHOD_NAMES
+            .iter()
+            .map(|&name| (name.to_owned(), name))
+            .chain(used_commands.iter().map(|&command| {
+                (command_map[command].method_name().to_owned(), command)
+            })),
+    );
+    let debug_callback = find_debug_callback(opts, &used_commands);
+    if debug_callback.is_some() {
+        output_debug_callback_field(out);
+    }
+    let has_glsl = value_set.contains_key("GL_SHADING_LANGUAGE_VERSION");
+    if opts.driver_info {
+        output_driver_info_fields(has_glsl, out);
+    }
 
     for ext in &opts.extensions {
-        println!(
+        writeln!(
+            out,
             "    pub has_{}: bool,",
             ext.strip_prefix("GL_").unwrap_or(ext)
         )
+        .unwrap()
     }
-    print!(
-        r#"}}
-
-use std::fmt;
-impl fmt::Debug for Procs {{
+    // Everything we need from `std` is also in `core`, but older compilers
+    // lack `core::ffi::CStr`, so only say `core` when we must.
+    let std_crate = if opts.no_std { "core" } else { "std" };
+    writeln!(out, "}}\n\nuse {}::fmt;", std_crate).unwrap();
+    if opts.driver_info {
+        output_driver_info_debug(has_glsl, out);
+    } else {
+        write!(
+            out,
+            r#"impl fmt::Debug for Procs {{
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {{
         write!(f, "Procs{{{{...}}}}")?;
         Ok(())
     }}
 }}
 "#
-    );
+        )
+        .unwrap();
+    }
     let mut need_missing_ext_panic = false;
-    for command in &command_order {
+    for command in command_order {
         if used_identifier_set.contains(command.as_str()) {
             if let Some(ext) = command_exts.get(command.as_str()) {
                 if !ext.is_empty() {
-                    command_map[command].output_dummy_imp(ext, &opts);
+                    command_map[command].output_dummy_imp(ext, opts, out);
                     need_missing_ext_panic = true;
                 }
             }
         }
     }
     if need_missing_ext_panic {
-        print!(
+        write!(
+            out,
             r#"
 #[inline(never)] fn missing_ext_panic(name: &str, ext: &str) -> ! {{
     panic!("{{}} called, but the requisite extension ({{}}) is not present",
@@ -223,142 +495,379 @@ impl fmt::Debug for Procs {{
 }}
 
 "#
-        );
+        )
+        .unwrap();
     }
-    print!(
-        r#"use std::mem::{{transmute, MaybeUninit}};
-use std::ffi::CStr;
+    write!(
+        out,
+        r#"use {0}::mem::{{transmute, MaybeUninit}};
+use {0}::ffi::CStr;
+"#,
+        std_crate
+    )
+    .unwrap();
+    write!(
+        out,
+        r#"/// Options for [`Procs::new_with_options`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcsOptions<'a> {{
+    /// Extensions to treat as absent, even if the driver advertises them.
+    pub disabled_extensions: &'a [&'a str],
+    /// Extensions to treat as present, even if the driver doesn't advertise
+    /// them.
+    pub enabled_extensions: &'a [&'a str],
+}}
+
+impl Procs {{
 "#
-    );
-    print!(
-        r#"impl Procs {{
-    pub fn new<E, F: Fn(&[u8])->Result<*const(),E>>(get_proc: F)
+    )
+    .unwrap();
+    if opts.no_std {
+        // There's no environment to read the disabled extensions from, so
+        // the caller has to pass them in themselves.
+        write!(
+            out,
+            r#"    pub fn new<E, F: Fn(&[u8])->Result<*const(),E>>(get_proc: F,
+                                                   disabled_extensions: &[u8])
                  -> Result<Procs, E> {{
 "#
-    );
+        )
+        .unwrap();
+        if opts.extensions.is_empty() {
+            write!(
+                out,
+                r#"        let _ = disabled_extensions;
+        Procs::new_with_options(get_proc, &ProcsOptions::default())
+"#
+            )
+            .unwrap();
+        } else {
+            write!(
+                out,
+                r#"        Procs::new_with_filter(get_proc,
+            |ext| extension_is_disabled(disabled_extensions, ext), &[])
+"#
+            )
+            .unwrap();
+        }
+    } else {
+        write!(
+            out,
+            r#"    pub fn new<E, F: Fn(&[u8])->Result<*const(),E>>(get_proc: F)
+                 -> Result<Procs, E> {{
+"#
+        )
+        .unwrap();
+        match opts.disabled_extensions_var {
+            Some(ref var) if !opts.extensions.is_empty() => write!(
+                out,
+                r#"        let disabled_extensions = std::env::var("{}");
+        let disabled_extensions = disabled_extensions.as_ref()
+            .map(|x| x.as_bytes()).unwrap_or(b"");
+        let disabled_extensions
+            = build_disabled_extension_list(disabled_extensions);
+        Procs::new_with_filter(get_proc,
+            |ext| disabled_extensions.contains(ext), &[])
+"#,
+                var
+            )
+            .unwrap(),
+            _ => writeln!(
+                out,
+                "        Procs::new_with_options(get_proc, \
+                 &ProcsOptions::default())"
+            )
+            .unwrap(),
+        }
+    }
+    write!(
+        out,
+        r#"    }}
+    pub fn new_with_options<E, F: Fn(&[u8])->Result<*const(),E>>
+        (get_proc: F, options: &ProcsOptions) -> Result<Procs, E> {{
+        Procs::new_with_filter(get_proc,
+            |ext| options.disabled_extensions.iter()
+                .any(|x| x.as_bytes() == ext),
+            options.enabled_extensions)
+    }}
+    fn new_with_filter<E, F: Fn(&[u8])->Result<*const(),E>,
+                       D: Fn(&[u8])->bool>
+        (get_proc: F, is_disabled: D, enabled_extensions: &[&str])
+        -> Result<Procs, E> {{
+"#
+    )
+    .unwrap();
+    if opts.extensions.is_empty() {
+        writeln!(out, "        let _ = (is_disabled, enabled_extensions);")
+            .unwrap();
+    }
     // if you *really* want a GL binding with no GL entry points in it, I'm not
     // gonna get in your way.
     let mut need_getprocs = false;
-    print!(
+    write!(
+        out,
         r#"        let mut procs: [MaybeUninit<*const()>; {}] = unsafe {{
         MaybeUninit::uninit().assume_init()
     }};
 "#,
         sorted_commands.len()
-    );
+    )
+    .unwrap();
     // initialize the procs before we try calling glGetString (duh)
     if let Some(&(start, stop)) = ext_proc_ranges.get("") {
         need_getprocs = true;
-        println!(
+        writeln!(
+            out,
             r#"        Procs::getprocs(&get_proc, &mut procs[{}..{}], &["#,
             start, stop
-        );
+        )
+        .unwrap();
         for i in start..stop {
-            println!("            b\"{}\\0\",", sorted_commands[i as usize]);
+            writeln!(
+                out,
+                "            b\"{}\\0\",",
+                sorted_commands[i as usize]
+            )
+            .unwrap();
         }
-        println!(r#"        ])?;"#);
+        writeln!(out, r#"        ])?;"#).unwrap();
     }
     for ext in &opts.extensions {
         if let Some(&(start, stop)) = ext_proc_ranges.get(ext.as_str()) {
             for i in start..stop {
-                println!(
+                writeln!(
+                    out,
                     "        procs[{}].write({}_null_imp as *const ());",
                     i, sorted_commands[i as usize]
-                );
+                )
+                .unwrap();
             }
         }
     }
-    print!(
+    write!(
+        out,
         r#"        let procs = unsafe {{ transmute(procs) }};
         #[allow(unused_mut)] let mut ret = Procs {{
             procs,
 "#
-    );
+    )
+    .unwrap();
+    if debug_callback.is_some() {
+        writeln!(out, "            debug_callback: Default::default(),")
+            .unwrap();
+    }
     for ext in &opts.extensions {
-        println!(
+        writeln!(
+            out,
             "            has_{}: false,",
             ext.strip_prefix("GL_").unwrap_or(ext)
-        );
+        )
+        .unwrap();
     }
-    println!("        }};");
-    if !opts.extensions.is_empty() {
-        print!(
-            r#"        let disabled_extensions = std::env::var("GL_DISABLED_EXTENSIONS");
-        let disabled_extensions = disabled_extensions.as_ref()
-            .map(|x| x.as_bytes()).unwrap_or(b"");
-        let disabled_extensions
-            = build_disabled_extension_list(disabled_extensions);
-"#
-        );
+    if opts.driver_info {
+        output_driver_info_init(has_glsl, out);
+    }
+    writeln!(out, "        }};").unwrap();
+    if opts.driver_info {
+        output_driver_info_capture(opts, has_glsl, out);
+    }
+    if !opts.extensions.is_empty() || opts.driver_info {
         if opts.version.needs_getstringi_extensions() {
             // both OpenGL and OpenGL ES switched to this method in version 3.0
             // and deprecated the previous one
-            print!(
+            write!(out,
                 r#"        let mut num_extensions = 0;
-        unsafe {{ ret.GetIntegerv(GL_NUM_EXTENSIONS, &mut num_extensions) }};
+        unsafe {{ ret.{}({}, &mut num_extensions) }};
         for i in 0 .. num_extensions as GLuint {{
-            let ext = unsafe {{CStr::from_ptr(transmute(ret.GetStringi(GL_EXTENSIONS, i)))}}.to_bytes();
-"#
-            );
+            let ext = unsafe {{CStr::from_ptr(transmute(ret.{}({}, i)))}}.to_bytes();
+"#,
+                command_map["glGetIntegerv"].method_name(),
+                constant_path("GL_NUM_EXTENSIONS", opts),
+                command_map["glGetStringi"].method_name(),
+                constant_path("GL_EXTENSIONS", opts),
+            ).unwrap();
         } else {
-            print!(
-                r#"        let extensions = unsafe {{CStr::from_ptr(transmute(ret.GetString(GL_EXTENSIONS)))}};
+            write!(out,
+                r#"        let extensions = unsafe {{CStr::from_ptr(transmute(ret.{}({})))}};
         let extensions = extensions.to_bytes();
         for ext in extensions.split(|x| *x == b' ') {{
-"#
-            );
+"#,
+                command_map["glGetString"].method_name(),
+                constant_path("GL_EXTENSIONS", opts),
+            ).unwrap();
         }
-        print!(
-            r#"            if disabled_extensions.contains(ext) {{ continue }}
-            match ext {{
-"#
-        );
-        for ext in &opts.extensions {
-            println!(
-                r#"                b"{}" => ret.has_{} = true,"#,
-                ext,
-                ext.strip_prefix("GL_").unwrap_or(ext)
-            );
+        if opts.driver_info {
+            writeln!(
+                out,
+                "            if !ext.is_empty() {{ \
+                 ret.extensions.push(String::from_utf8_lossy(ext).into_owned()) }}"
+            )
+            .unwrap();
         }
-        print!(
-            r#"            _ => (),
-            }}
+        if opts.extensions.is_empty() {
+            writeln!(out, "        }}").unwrap();
+        } else {
+            write!(
+                out,
+                r#"            if !is_disabled(ext) {{ ret.mark_extension(ext) }}
+        }}
+        for ext in enabled_extensions {{
+            ret.mark_extension(ext.as_bytes());
         }}
 "#
-        );
+            )
+            .unwrap();
+        }
+    }
+    if !promoted.is_empty() && opts.driver_info {
+        writeln!(
+            out,
+            "        let version = gl_version(ret.version.as_bytes());"
+        )
+        .unwrap();
+    } else if !promoted.is_empty() {
+        writeln!(
+            out,
+            "        let version = gl_version(ret.gl_string({}));",
+            constant_path("GL_VERSION", opts),
+        )
+        .unwrap();
+    }
+    // Promoted extensions whose commands have different names in core
+    let mut renamed = HashSet::new();
+    for (ext, promotion) in promoted {
+        if let Some(&(start, stop)) = ext_proc_ranges.get(ext.as_str()) {
+            if (start..stop).any(|i| {
+                let command = sorted_commands[i as usize];
+                promotion.commands[command] != command
+            }) {
+                renamed.insert(ext.as_str());
+            }
+        }
+    }
+    for ext in &opts.extensions {
+        if let Some(promotion) = promoted.get(ext) {
+            let name_for_has = ext.strip_prefix("GL_").unwrap_or(ext);
+            let condition = format!(
+                "version >= ({}, {}) && !is_disabled(b\"{}\")",
+                promotion.version.0, promotion.version.1, ext
+            );
+            if renamed.contains(ext.as_str()) {
+                // (we'll load the core versions of its commands, below)
+                write!(
+                    out,
+                    r#"        let core_{0} = {1};
+        if core_{0} {{ ret.has_{0} = true }}
+"#,
+                    name_for_has, condition
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "        if {} {{ ret.has_{} = true }}",
+                    condition, name_for_has
+                )
+                .unwrap();
+            }
+        }
     }
     for ext in &opts.extensions {
         if let Some(&(start, stop)) = ext_proc_ranges.get(ext.as_str()) {
             let name_for_has = ext.strip_prefix("GL_").unwrap_or(ext);
             need_getprocs = true;
-            print!(
+            write!(
+                out,
                 r#"        if ret.has_{} {{
             Procs::getprocs(&get_proc,
-                            unsafe {{ transmute(&mut ret.procs[{}..{}]) }}, &[
-"#,
+                            unsafe {{ transmute(&mut ret.procs[{}..{}]) }},"#,
                 name_for_has, start, stop
-            );
-            for i in start..stop {
-                println!(
-                    "                b\"{}\\0\",",
-                    sorted_commands[i as usize]
-                );
+            )
+            .unwrap();
+            let output_names = |out: &mut W, core: bool| {
+                for i in start..stop {
+                    let command = sorted_commands[i as usize];
+                    let command = match promoted.get(ext) {
+                        Some(promotion) if core => {
+                            &promotion.commands[command]
+                        }
+                        _ => command,
+                    };
+                    writeln!(out, "                b\"{}\\0\",", command)
+                        .unwrap();
+                }
+            };
+            if renamed.contains(ext.as_str()) {
+                writeln!(out, "\n            if core_{} {{ &[", name_for_has)
+                    .unwrap();
+                output_names(out, true);
+                writeln!(out, "            ] }} else {{ &[").unwrap();
+                output_names(out, false);
+                writeln!(out, "            ] }})?;\n        }}").unwrap();
+            } else {
+                writeln!(out, " &[").unwrap();
+                output_names(out, false);
+                writeln!(out, "            ])?;\n        }}").unwrap();
             }
-            print!(
-                r#"            ])?;
-        }}
-"#
-            );
         }
     }
 
-    print!(
+    write!(
+        out,
         r#"        Ok(ret)
     }}
 "#
-    );
+    )
+    .unwrap();
+    if !opts.extensions.is_empty() {
+        write!(
+            out,
+            r#"    fn mark_extension(&mut self, ext: &[u8]) {{
+        match ext {{
+"#
+        )
+        .unwrap();
+        for ext in &opts.extensions {
+            writeln!(
+                out,
+                r#"            b"{}" => self.has_{} = true,"#,
+                ext,
+                ext.strip_prefix("GL_").unwrap_or(ext)
+            )
+            .unwrap();
+        }
+        write!(
+            out,
+            r#"            _ => (),
+        }}
+    }}
+"#
+        )
+        .unwrap();
+    }
+    if opts.driver_info || !promoted.is_empty() {
+        write!(
+            out,
+            r#"    /// Returns what `glGetString` says about `name`, or nothing if it
+    /// returns null (as it does when `name` isn't valid).
+    fn gl_string(&self, name: GLenum) -> &[u8] {{
+        let string = unsafe {{ self.{}(name) }};
+        if string.is_null() {{
+            return &[];
+        }}
+        // Unsafe justification: the GL returns a null-terminated string
+        unsafe {{ CStr::from_ptr(string as *const _) }}.to_bytes()
+    }}
+"#,
+            command_map["glGetString"].method_name()
+        )
+        .unwrap();
+    }
+    if opts.driver_info {
+        output_driver_info_methods(has_glsl, out);
+    }
     if need_getprocs {
-        print!(
+        write!(out,
             r#"    fn getprocs<E, F: Fn(&[u8])->Result<*const(),E>>(get_proc: &F, range: &mut[MaybeUninit<*const ()>], names: &[&[u8]]) -> Result<(), E> {{
         debug_assert_eq!(range.len(), names.len());
         for i in 0..range.len() {{
@@ -367,19 +876,107 @@ use std::ffi::CStr;
         Ok(())
     }}
 "#
-        );
+        ).unwrap();
     }
-    for command in &command_order {
+    for command in command_order {
         if used_identifier_set.contains(command.as_str())
             && command_exts.contains_key(command.as_str())
         {
-            command_map[command]
-                .output_imp(&opts, proc_indices[command.as_str()]);
+            output_deprecation(deprecated, command, "    ", out);
+            command_map[command].output_imp(
+                opts,
+                proc_indices[command.as_str()],
+                out,
+            );
         }
     }
-    println!("}}");
-    if !opts.extensions.is_empty() {
-        print!(
+    writeln!(out, "}}").unwrap();
+    if opts.global {
+        write!(
+            out,
+            r#"
+std::thread_local! {{
+    static CURRENT_PROCS: std::cell::Cell<*const Procs>
+        = std::cell::Cell::new(std::ptr::null());
+}}
+
+/// Makes `procs` the `Procs` that the free functions in this module call
+/// through, on this thread only. Call this whenever you make a different GL
+/// context current.
+///
+/// # Safety
+///
+/// `procs` must not be moved or dropped while it is current. Make another
+/// `Procs` current, or call `clear_current`, first.
+pub unsafe fn make_current(procs: &Procs) {{
+    CURRENT_PROCS.with(|x| x.set(procs));
+}}
+
+/// Makes no `Procs` current on this thread. Calling any of the free functions
+/// in this module will panic until `make_current` is called again.
+pub fn clear_current() {{
+    CURRENT_PROCS.with(|x| x.set(std::ptr::null()));
+}}
+
+#[inline(always)] fn current_procs() -> &'static Procs {{
+    let procs = CURRENT_PROCS.with(|x| x.get());
+    if procs.is_null() {{ no_current_procs_panic() }}
+    // Unsafe justification: `make_current`'s contract
+    unsafe {{ &*procs }}
+}}
+
+#[inline(never)] fn no_current_procs_panic() -> ! {{
+    panic!("GL function called, but no Procs is current on this thread");
+}}
+
+"#
+        )
+        .unwrap();
+        for command in command_order {
+            if used_identifier_set.contains(command.as_str())
+                && command_exts.contains_key(command.as_str())
+            {
+                output_deprecation(deprecated, command, "", out);
+                command_map[command].output_global_imp(opts, out);
+            }
+        }
+    }
+    if let Some(command) = debug_callback {
+        output_debug_callback(opts, &command_map[command], out);
+    }
+    if used_commands.contains(&"glGetError") {
+        output_gl_error(
+            opts,
+            &command_map["glGetError"],
+            value_map,
+            &value_set,
+            group_map,
+            out,
+        );
+    }
+    if opts.object_wrappers {
+        output_object_wrappers(opts, command_map, &used_commands, out);
+    }
+    if !opts.extensions.is_empty() && opts.no_std {
+        write!(
+            out,
+            r#"
+fn extension_is_disabled(disabled_extensions: &[u8], ext: &[u8]) -> bool {{
+    disabled_extensions.split(|&x| {{
+        !((x >= b'0' && x <= b'9')
+          || (x >= b'A' && x <= b'Z')
+          || (x >= b'a' && x <= b'z')
+          || (x == b'_'))
+    }}).any(|x| !x.is_empty() && x == ext)
+}}
+"#
+        )
+        .unwrap();
+    } else if !opts.extensions.is_empty()
+        && opts.disabled_extensions_var.is_some()
+    {
+        write!(
+            out,
             r#"
 fn build_disabled_extension_list(disabled_extensions: &[u8])
             -> std::collections::HashSet<&[u8]> {{
@@ -396,6 +993,279 @@ fn build_disabled_extension_list(disabled_extensions: &[u8])
     }}).collect()
 }}
 "#
+        )
+        .unwrap();
+    }
+    if !promoted.is_empty() {
+        write!(
+            out,
+            r#"
+/// Finds the version number in a `GL_VERSION` string, such as `(4, 6)` in
+/// `4.6.0 NVIDIA 550.54.14` or `(3, 2)` in `OpenGL ES 3.2 Mesa 24.0.5`.
+fn gl_version(version: &[u8]) -> (u32, u32) {{
+    let mut numbers = version.split(|x| !x.is_ascii_digit())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.iter().fold(0u32, |a, x| {{
+            a.saturating_mul(10).saturating_add((x - b'0') as u32)
+        }}));
+    (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+}}
+"#
+        )
+        .unwrap();
+    }
+}
+
+fn read_source(opts: &CmdLine) -> Vec<u8> {
+    let mut source = match opts.xml_path {
+        Some(ref path) => match fs::read(path) {
+            Ok(source) => source,
+            Err(x) => {
+                eprintln!("{}: {}", path.display(), x);
+                exit(1);
+            }
+        },
+        #[cfg(feature = "bundled-registry")]
+        None => bundled::read_registry(),
+        #[cfg(not(feature = "bundled-registry"))]
+        None => unreachable!(),
+    };
+    // skip a byte order mark if there is one
+    if source.starts_with(b"\xEF\xBB\xBF") {
+        source.drain(..3);
+    }
+    source
+}
+
+fn parse_registry(source: &[u8]) -> dom::Element {
+    let xml = dom::read_xml(source);
+    assert!(xml.get_name() == "registry");
+    xml
+}
+
+fn read_registry(opts: &CmdLine) -> dom::Element {
+    parse_registry(&read_source(opts))
+}
+
+/// Everything `generate` needs from the registry to generate one file. This
+/// is what goes in the registry cache.
+#[derive(Serialize, Deserialize)]
+struct Gathered {
+    /// Each target's extensions, as `distribute_extensions` left them.
+    extensions: Vec<Vec<String>>,
+    registries: Vec<Registry>,
+    /// The text of the registry's `<comment>`s.
+    comments: String,
+}
+
+fn gather(xml: &dom::Element, targets: &[CmdLine]) -> Gathered {
+    let mut targets = targets.to_vec();
+    if targets.len() > 1 {
+        distribute_extensions(xml, &mut targets);
+    }
+    Gathered {
+        registries: targets
+            .iter()
+            .map(|opts| gather_registry(xml, opts))
+            .collect(),
+        extensions: targets.into_iter().map(|opts| opts.extensions).collect(),
+        comments: gather_comments(xml),
+    }
+}
+
+/// Generates a file containing a binding for each of the targets.
+fn generate<W: Write>(
+    gathered: Gathered,
+    mut targets: Vec<CmdLine>,
+    out: &mut W,
+) {
+    let Gathered {
+        extensions,
+        registries,
+        comments,
+    } = gathered;
+    for (opts, extensions) in targets.iter_mut().zip(extensions) {
+        opts.extensions = extensions;
+    }
+    let used_identifier_set = get_used_identifiers(&targets[0]);
+    let mut used_identifier_sets: Vec<UsedIdentifiers> = targets
+        .iter()
+        .map(|_| used_identifier_set.clone())
+        .collect();
+    let features: Vec<[HashMap<&str, &str>; 3]> = targets
+        .iter()
+        .zip(&registries)
+        .zip(&mut used_identifier_sets)
+        .map(|((opts, registry), used_identifier_set)| {
+            resolve_binding(opts, registry, used_identifier_set)
+        })
+        .collect();
+    write!(out,
+        r"#![allow(dead_code,deprecated,non_snake_case,non_upper_case_globals,unused_imports,clippy::all)]
+
+//! This module was generated using the rglgen crate.
+"
+    ).unwrap();
+    if targets[0].xml_path.is_none() {
+        writeln!(
+            out,
+            "//! It was generated from {}.",
+            targets[0].describe_xml()
+        )
+        .unwrap();
+    }
+    if targets.len() == 1 {
+        let opts = &targets[0];
+        output_binding_doc(opts, "It is", out);
+        output_xml_comments(&comments, out);
+        let features = features.into_iter().next().unwrap();
+        output_binding(
+            opts,
+            &registries[0],
+            features,
+            &used_identifier_sets[0],
+            &HashSet::new(),
+            out,
         );
+        return;
+    }
+    writeln!(
+        out,
+        "//! It contains several bindings, each in its own module:"
+    )
+    .unwrap();
+    for opts in &targets {
+        writeln!(
+            out,
+            "//! - `{}`: {}",
+            opts.version.module_name(),
+            opts.version
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "//!\n//! Types they have in common are in the `types` module."
+    )
+    .unwrap();
+    output_xml_comments(&comments, out);
+    let shared_types = find_shared_types(&registries, &features);
+    write!(
+        out,
+        r"
+pub mod types {{
+//! Types shared by all the bindings in this file.
+"
+    )
+    .unwrap();
+    // Shared types are defined identically everywhere they're used, so
+    // output each one as the first binding that uses it sees it.
+    for typ in &shared_types {
+        for (registry, [type_set, _, _]) in registries.iter().zip(&features) {
+            if type_set.contains_key(typ) {
+                registry.type_map[*typ].output(&targets[0], out);
+                break;
+            }
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    let shared_types: HashSet<&str> = shared_types.into_iter().collect();
+    for (((opts, registry), features), used_identifier_set) in targets
+        .iter()
+        .zip(&registries)
+        .zip(features)
+        .zip(&used_identifier_sets)
+    {
+        writeln!(out, "\npub mod {} {{", opts.version.module_name()).unwrap();
+        output_binding_doc(opts, "This is", out);
+        writeln!(out, "\nuse super::types::*;").unwrap();
+        output_binding(
+            opts,
+            registry,
+            features,
+            used_identifier_set,
+            &shared_types,
+            out,
+        );
+        writeln!(out, "}}").unwrap();
+    }
+}
+
+fn main() {
+    let outputs = match parse_cmdline() {
+        None => exit(1),
+        Some(Mode::Generate(outputs)) => outputs,
+        Some(Mode::Diff(old, new)) => {
+            let (mut old, mut new) = (*old, *new);
+            let old_xml = read_registry(&old);
+            let new_xml = if new.xml_path == old.xml_path {
+                None
+            } else {
+                Some(read_registry(&new))
+            };
+            let new_xml = new_xml.as_ref().unwrap_or(&old_xml);
+            if new.xml_path == old.xml_path {
+                let mut targets = [old, new];
+                distribute_extensions(&old_xml, &mut targets);
+                [old, new] = targets;
+            }
+            let old_registry = gather_registry(&old_xml, &old);
+            let new_registry = gather_registry(new_xml, &new);
+            let stdout = io::stdout();
+            let mut out = io::BufWriter::new(stdout.lock());
+            diff::output_diff(
+                &old,
+                &old_registry,
+                &new,
+                &new_registry,
+                &mut out,
+            );
+            out.flush().unwrap();
+            return;
+        }
+    };
+    // outputs will usually share the same registry, so only read it (and
+    // parse it, if need be) once
+    let mut registries: HashMap<
+        Option<PathBuf>,
+        (Vec<u8>, Option<dom::Element>),
+    > = HashMap::new();
+    for output in outputs {
+        let opts = &output.targets[0];
+        let (source, xml) = registries
+            .entry(opts.xml_path.clone())
+            .or_insert_with(|| (read_source(opts), None));
+        let source: &[u8] = source;
+        let key: Vec<String> =
+            output.targets.iter().map(CmdLine::cache_key).collect();
+        let gathered = cache::load_cached(
+            source,
+            &key.join("\n"),
+            opts.registry_cache.as_deref(),
+            || {
+                let xml = xml.get_or_insert_with(|| parse_registry(source));
+                gather(xml, &output.targets)
+            },
+        );
+        match output.path {
+            None => {
+                let stdout = io::stdout();
+                let mut out = io::BufWriter::new(stdout.lock());
+                generate(gathered, output.targets, &mut out);
+                out.flush().unwrap();
+            }
+            Some(path) => {
+                let file = match fs::File::create(&path) {
+                    Ok(file) => file,
+                    Err(x) => {
+                        eprintln!("{}: {}", path.display(), x);
+                        exit(1);
+                    }
+                };
+                let mut out = io::BufWriter::new(file);
+                generate(gathered, output.targets, &mut out);
+                out.flush().unwrap();
+            }
+        }
     }
 }
@@ -0,0 +1,112 @@
+use std::{collections::HashMap, process::exit};
+
+use crate::cmdline::CmdLine;
+
+/// Names of things we generate ourselves, alongside the GL's methods and
+/// free functions.
+pub const RESERVED_METHOD_NAMES: &[&str] = &[
+    "new",
+    "new_with_options",
+    "getprocs",
+    "mark_extension",
+    "set_debug_callback",
+    "check_error",
+    "make_current",
+    "clear_current",
+    "current_procs",
+    "no_current_procs_panic",
+    "missing_ext_panic",
+    "extension_is_disabled",
+    "enum_name",
+    "enum_name_in",
+    "gl_version",
+    "vendor",
+    "renderer",
+    "version",
+    "glsl_version",
+    "extensions",
+    "driver_string",
+];
+
+/// Turns a CamelCase name into snake_case, e.g. `BindBuffer` into
+/// `bind_buffer`. Dimensions stay together with their numbers, so
+/// `TexImage2D` becomes `tex_image2d`.
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut ret = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next = chars.get(i + 1).cloned().unwrap_or('_');
+            let new_word = if prev.is_ascii_lowercase() {
+                true
+            } else if prev.is_ascii_digit() {
+                c != 'D'
+            } else {
+                // the end of an acronym, as in `GLSLVersion`
+                prev.is_ascii_uppercase() && next.is_ascii_lowercase()
+            };
+            if new_word {
+                ret.push('_');
+            }
+        }
+        ret.push(c.to_ascii_lowercase());
+    }
+    ret
+}
+
+/// Strips the vendor suffix of `ext` (e.g. `ARB` for `GL_ARB_debug_output`)
+/// from `name`, if it has it.
+pub fn strip_vendor_suffix<'a>(name: &'a str, ext: &str) -> Option<&'a str> {
+    let vendor = ext.split('_').nth(1)?;
+    name.strip_suffix(vendor)?.strip_suffix('_')
+}
+
+/// The name a constant is output with: unchanged, or, with
+/// `--constants-module`, without its `GL_` prefix (and with a `_` in its
+/// place if it would otherwise start with a digit, as `GL_2D` does).
+pub fn constant_name(name: &str, opts: &CmdLine) -> String {
+    if opts.constants_module.is_none() {
+        return name.to_owned();
+    }
+    let bare = name.strip_prefix("GL_").unwrap_or(name);
+    if bare.starts_with(|x: char| x.is_ascii_digit()) {
+        format!("_{}", bare)
+    } else {
+        bare.to_owned()
+    }
+}
+
+/// How to refer to a constant from the top level of the binding.
+pub fn constant_path(name: &str, opts: &CmdLine) -> String {
+    match opts.constants_module {
+        None => name.to_owned(),
+        Some(ref module) => {
+            format!("{}::{}", module, constant_name(name, opts))
+        }
+    }
+}
+
+/// Makes sure no two registry names (the second of each pair) have been
+/// turned into the same Rust name (the first).
+pub fn check_collisions<'a, I: IntoIterator<Item = (String, &'a str)>>(
+    names: I,
+) {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    let mut errors = false;
+    for (rust_name, name) in names {
+        if let Some(other) = seen.insert(rust_name.clone(), name) {
+            if other != name {
+                eprintln!(
+                    "{} and {} would both be named {}",
+                    other, name, rust_name
+                );
+                errors = true;
+            }
+        }
+    }
+    if errors {
+        eprintln!("Errors have occurred, panicking");
+        exit(1);
+    }
+}
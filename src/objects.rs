@@ -0,0 +1,286 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use crate::{cmdline::CmdLine, commands::*};
+
+/// How many objects a command makes or deletes at once.
+#[derive(Clone, Copy, PartialEq)]
+enum Arity {
+    /// `(n: GLsizei, names: *T)`, like `glGenBuffers`
+    Many,
+    /// A single name, returned (like `glCreateShader`) or taken (like
+    /// `glDeleteShader`).
+    One,
+}
+
+/// If a command looks like it makes (or deletes) objects, returns how many
+/// it does at once, their class (if gl.xml gives it), and the type of their
+/// names.
+fn object_shape(
+    command: &Command,
+    making: bool,
+) -> Option<(Arity, Option<&str>, &str)> {
+    let params = command.get_param_shapes();
+    if params.len() == 2
+        && params[0].base == "GLsizei"
+        && !params[0].pointer
+        && params[1].pointer
+    {
+        let class = params[1].class.as_deref();
+        return Some((Arity::Many, class, &params[1].base));
+    }
+    let shape = if making {
+        command.get_return_shape()
+    } else if params.len() == 1 {
+        &params[0]
+    } else {
+        return None;
+    };
+    if shape.pointer {
+        return None;
+    }
+    Some((Arity::One, shape.class.as_deref(), &shape.base))
+}
+
+/// Works out the class of object that commands like `glGenVertexArraysOES`
+/// make from the rest of their name (here `vertex array`), for registries
+/// that predate the `class` attribute.
+fn class_from_suffix(suffix: &str) -> String {
+    // the vendor suffix is the only run of capitals in a command name
+    let vendor = suffix
+        .bytes()
+        .rev()
+        .take_while(|x| x.is_ascii_uppercase())
+        .count();
+    let suffix = if vendor > 1 {
+        &suffix[..suffix.len() - vendor]
+    } else {
+        suffix
+    };
+    let singular = match suffix.strip_suffix("ies") {
+        Some(stem) => format!("{}y", stem),
+        None => suffix.strip_suffix('s').unwrap_or(suffix).to_owned(),
+    };
+    let mut ret = String::new();
+    for x in singular.chars() {
+        if x.is_ascii_uppercase() && !ret.is_empty() {
+            ret.push(' ');
+        }
+        ret.push(x.to_ascii_lowercase());
+    }
+    ret
+}
+
+/// An owning wrapper for one class of object.
+struct Wrapper<'a> {
+    name: String,
+    class: String,
+    base: &'a str,
+    delete: &'a Command,
+    arity: Arity,
+    /// Constructor names, and the commands they call.
+    constructors: Vec<(&'static str, &'a Command)>,
+}
+
+/// Finds every pair of commands like `glGenBuffers`/`glDeleteBuffers` or
+/// `glCreateShader`/`glDeleteShader` among the used ones, and groups them by
+/// the class of object they make. If gl.xml doesn't give the class, it's
+/// worked out from the commands' names.
+fn find_wrappers<'a>(
+    command_map: &'a HashMap<String, Command>,
+    used_commands: &[&str],
+) -> Vec<Wrapper<'a>> {
+    let used: HashSet<&str> = used_commands.iter().cloned().collect();
+    let mut wrappers: Vec<Wrapper> = Vec::new();
+    for command in used_commands {
+        let make = &command_map[*command];
+        let (verb, suffix) = if let Some(x) = command.strip_prefix("glGen") {
+            ("Gen", x)
+        } else if let Some(x) = command.strip_prefix("glCreate") {
+            ("Create", x)
+        } else {
+            continue;
+        };
+        let delete = format!("glDelete{}", suffix);
+        if !used.contains(delete.as_str()) {
+            continue;
+        }
+        let delete = &command_map[&delete];
+        let (arity, class, base) = match object_shape(make, true) {
+            Some(x) => x,
+            None => continue,
+        };
+        if object_shape(delete, false) != Some((arity, class, base)) {
+            continue;
+        }
+        let constructor = match (verb, arity) {
+            ("Gen", Arity::Many) => "new",
+            ("Create", Arity::Many) => "create",
+            ("Create", Arity::One) => "new",
+            _ => continue,
+        };
+        let class = match class {
+            Some(class) => class.to_owned(),
+            None => class_from_suffix(suffix),
+        };
+        let name = handle_type_name(&class);
+        match wrappers.iter_mut().find(|x| x.name == name) {
+            // (e.g. both `glGenBuffers` and `glGenBuffersARB` are bound; the
+            // core one comes first and wins)
+            Some(wrapper) => {
+                if wrapper.delete.get_name() == delete.get_name()
+                    && !wrapper.constructors.iter().any(|x| x.0 == constructor)
+                {
+                    wrapper.constructors.push((constructor, make));
+                }
+            }
+            None => wrappers.push(Wrapper {
+                name,
+                class,
+                base,
+                delete,
+                arity,
+                constructors: vec![(constructor, make)],
+            }),
+        }
+    }
+    wrappers.sort_by(|a, b| a.name.cmp(&b.name));
+    wrappers
+}
+
+/// Outputs the `objects` module, containing an owning wrapper for every
+/// class of object that the used commands can both make and delete. If
+/// there are none, leaves the module out.
+pub fn output_object_wrappers<W: Write>(
+    opts: &CmdLine,
+    command_map: &HashMap<String, Command>,
+    used_commands: &[&str],
+    out: &mut W,
+) {
+    let wrappers = find_wrappers(command_map, used_commands);
+    if wrappers.is_empty() {
+        eprintln!("warning: none of the used commands both make and delete objects, so there is no `objects` module");
+        return;
+    }
+    let std_crate = if opts.no_std { "core" } else { "std" };
+    write!(
+        out,
+        r#"
+pub mod objects {{
+//! Owning wrappers for GL objects, which delete them when dropped.
+//!
+//! # Safety
+//!
+//! Making, using and dropping these objects all call into GL. The context
+//! that the `Procs` they were made with belongs to must be current whenever
+//! any of those happen.
+use super::*;
+"#
+    )
+    .unwrap();
+    for wrapper in wrappers {
+        let Wrapper {
+            name,
+            class,
+            base,
+            delete,
+            arity,
+            constructors,
+        } = wrapper;
+        write!(
+            out,
+            r#"
+/// A {class} object, deleted with `{delete}` when dropped.
+#[derive(Debug)]
+pub struct {name}<'a> {{
+    procs: &'a Procs,
+    name: super::{base},
+}}
+
+impl<'a> {name}<'a> {{
+"#,
+            class = class,
+            delete = delete.get_name(),
+            name = name,
+            base = base,
+        )
+        .unwrap();
+        for (constructor, make) in constructors {
+            writeln!(
+                out,
+                "    /// Makes a new one with `{}`.",
+                make.get_name()
+            )
+            .unwrap();
+            match arity {
+                Arity::Many => write!(
+                    out,
+                    r#"    pub unsafe fn {}(procs: &'a Procs) -> Self {{
+        let mut name = {}::mem::MaybeUninit::uninit();
+        unsafe {{ procs.{}(1, name.as_mut_ptr()) }};
+        Self {{ procs, name: unsafe {{ name.assume_init() }} }}
+    }}
+"#,
+                    constructor,
+                    std_crate,
+                    make.method_name()
+                ),
+                Arity::One => write!(
+                    out,
+                    r#"    pub unsafe fn {}(procs: &'a Procs{}{}) -> Self {{
+        Self {{ procs, name: unsafe {{ procs.{}({}) }} }}
+    }}
+"#,
+                    constructor,
+                    if make.get_params().is_empty() {
+                        ""
+                    } else {
+                        ", "
+                    },
+                    make.get_params(),
+                    make.method_name(),
+                    make.get_param_names()
+                ),
+            }
+            .unwrap();
+        }
+        let delete_call = match arity {
+            Arity::Many => format!("{}(1, &self.name)", delete.method_name()),
+            Arity::One => format!("{}(self.name)", delete.method_name()),
+        };
+        write!(
+            out,
+            r#"    /// Takes ownership of an existing object, which will be deleted when
+    /// this is dropped.
+    pub unsafe fn from_raw(procs: &'a Procs, name: super::{base}) -> Self {{
+        Self {{ procs, name }}
+    }}
+    pub fn name(&self) -> super::{base} {{
+        self.name
+    }}
+    /// Gives up ownership of the object, without deleting it.
+    pub fn into_raw(self) -> super::{base} {{
+        let name = self.name;
+        {std_crate}::mem::forget(self);
+        name
+    }}
+}}
+
+impl Drop for {name}<'_> {{
+    fn drop(&mut self) {{
+        // Unsafe justification: the module's safety contract
+        unsafe {{ self.procs.{delete_call} }}
+    }}
+}}
+"#,
+            base = base,
+            std_crate = std_crate,
+            name = name,
+            delete_call = delete_call,
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+}
@@ -1,22 +1,47 @@
 use std::{collections::HashMap, io::Write};
 
-use lazy_static::lazy_static;
-use regex::bytes::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cmdline::CmdLine,
+    declarator::*,
     dom::{Element, Node},
 };
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Type {
     code: Option<String>,
+    requires: Vec<String>,
 }
 
 impl Type {
-    pub fn output(&self, _opts: &CmdLine) {
+    pub fn output<W: Write>(&self, _opts: &CmdLine, out: &mut W) {
         if let Some(ref code) = self.code {
-            println!("{}", code);
+            writeln!(out, "{}", code).unwrap();
+        }
+    }
+    pub fn get_requires(&self) -> &[String] {
+        &self.requires
+    }
+}
+
+/// Adds every type that a type in the set depends on (via its `requires`
+/// attribute, or by naming it in its definition) to the set, recursively.
+/// Dependencies are attributed to the same extension as their dependents.
+pub fn close_type_set<'a>(
+    type_map: &'a HashMap<String, Type>,
+    type_set: &mut HashMap<&'a str, &'a str>,
+) {
+    let mut pending: Vec<(&str, &str)> =
+        type_set.iter().map(|(typ, ext)| (*typ, *ext)).collect();
+    while let Some((typ, ext)) = pending.pop() {
+        if let Some(typ) = type_map.get(typ) {
+            for req in &typ.requires {
+                if !type_set.contains_key(req.as_str()) {
+                    type_set.insert(req, ext);
+                    pending.push((req, ext));
+                }
+            }
         }
     }
 }
@@ -46,165 +71,290 @@ fn gather_text_content_and_search_for_name<W: Write>(
     }
 }
 
-fn space_to_underscore(x: &u8) -> u8 {
-    if *x == b' ' {
-        b'_'
+/// A Rust type that can only be pointed to, standing in for a C struct whose
+/// contents we don't know.
+fn opaque_struct(name: &str) -> String {
+    format!(
+        "#[repr(C)] #[allow(non_camel_case_types)] pub struct {} {{ _private: [u8; 0] }}",
+        rust_type_name(name)
+    )
+}
+
+/// Translates a named type that isn't a builtin, adding it to `requires`.
+fn resolve_type(
+    map: &mut HashMap<String, Type>,
+    order: &mut Vec<String>,
+    name: &str,
+    requires: &mut Vec<String>,
+) -> String {
+    if !map.contains_key(name) {
+        if !name.starts_with("struct ") {
+            panic!("Can't find the Rust equivalent to: {}", name);
+        }
+        // a struct the registry mentions without declaring, like
+        // `struct __GLsync`
+        map.insert(
+            name.to_owned(),
+            Type {
+                code: Some(opaque_struct(name)),
+                requires: Vec::new(),
+            },
+        );
+        order.push(name.to_owned());
+    }
+    if !requires.iter().any(|x| x == name) {
+        requires.push(name.to_owned());
+    }
+    rust_type_name(name)
+}
+
+fn type_parse_failure(name: &str, why: &str) -> ! {
+    eprintln!("!!!!!!!!!!!!!!!!!!!!!!");
+    eprintln!("! TYPE PARSE FAILURE !");
+    eprintln!("!!!!!!!!!!!!!!!!!!!!!!");
+    eprintln!(
+        "We couldn't understand the definition for `{}`: {}",
+        name, why
+    );
+    panic!("Type parse failure");
+}
+
+/// Maps a C preprocessor macro that identifies a platform to the equivalent
+/// `cfg` predicate.
+fn platform_macro_to_cfg(name: &str, macro_name: &str) -> &'static str {
+    match macro_name {
+        "__APPLE__" => r#"target_vendor = "apple""#,
+        "_WIN32" => "windows",
+        "_WIN64" => r#"all(windows, target_pointer_width = "64")"#,
+        "__ANDROID__" => r#"target_os = "android""#,
+        "__linux__" => r#"target_os = "linux""#,
+        "__unix__" => "unix",
+        "__EMSCRIPTEN__" => r#"target_os = "emscripten""#,
+        x => panic!(
+            "{}'s definition depends on an unknown preprocessor macro: {}",
+            name, x
+        ),
+    }
+}
+
+fn combine_cfg(op: &str, mut predicates: Vec<String>) -> String {
+    if predicates.len() == 1 {
+        predicates.pop().unwrap()
     } else {
-        *x
+        format!("{}({})", op, predicates.join(", "))
+    }
+}
+
+/// Translates the condition of an `#if` or `#elif` into a `cfg` predicate.
+/// Only `defined(X)`, `!`, `&&` and `||` are understood, which is all that
+/// the registry uses.
+fn preprocessor_condition_to_cfg(name: &str, condition: &str) -> String {
+    let alternatives = condition
+        .split("||")
+        .map(|alternative| {
+            let terms = alternative
+                .split("&&")
+                .map(|term| {
+                    let term = term.trim();
+                    let (negated, term) = match term.strip_prefix('!') {
+                        Some(term) => (true, term.trim()),
+                        None => (false, term),
+                    };
+                    let macro_name = match term.strip_prefix("defined") {
+                        Some(x) => x
+                            .trim()
+                            .trim_start_matches('(')
+                            .trim_end_matches(')')
+                            .trim(),
+                        None => panic!(
+                            "{}'s definition has an unsupported preprocessor \
+                             condition: {}",
+                            name, condition
+                        ),
+                    };
+                    let predicate = platform_macro_to_cfg(name, macro_name);
+                    if negated {
+                        format!("not({})", predicate)
+                    } else {
+                        predicate.to_owned()
+                    }
+                })
+                .collect();
+            combine_cfg("all", terms)
+        })
+        .collect();
+    combine_cfg("any", alternatives)
+}
+
+/// Explains why a type's definition depends on the platform, for types that
+/// need explaining. Goes before its `#[cfg]`'d items.
+fn conditional_type_comment(name: &str) -> Option<&'static str> {
+    match name {
+        "GLhandleARB" => Some(
+            "// For historical reasons, this definition differs between macOS and other
+// platforms. When the extension was promoted to core in GL 2.0, the definition
+// was tightened. It's best to use the core versions of the routines that need
+// handles rather than the extensions.
+",
+        ),
+        _ => None,
     }
 }
 
-fn c_type_to_rust_type(
+/// Translates a type whose definition is wrapped in `#ifdef`/`#else`/
+/// `#endif` (like `GLhandleARB`), emitting each branch under an equivalent
+/// `#[cfg(...)]`.
+fn translate_conditional_type(
+    name: &str,
+    text: &[u8],
     map: &mut HashMap<String, Type>,
-    c_type: &[u8],
+    order: &mut Vec<String>,
     requires: &mut Vec<String>,
     opts: &CmdLine,
 ) -> Vec<u8> {
-    lazy_static! {
-        static ref CONDENSE_SPACES_1: Regex
-            = Regex::new(r#"^ +"#).unwrap();
-        static ref CONDENSE_SPACES_2: Regex
-            = Regex::new(r#" +(\*|$)"#).unwrap();
-        static ref STRUCT_MUNCHER: Regex
-            = Regex::new(r#"struct [_a-zA-Z][_a-zA-Z0-9]*"#).unwrap();
-        static ref POINTER_MUNCHER: Regex
-            = Regex::new(r#"^((?:const )?)([^\*]+) *(\**)$"#).unwrap();
-        static ref STATIC_TYPES_LIBC: HashMap<&'static [u8], &'static [u8]>
-            = [
-                // We can't count on these to map to particular Rust types for
-                // the most part. For most PC/mobile platforms it would be
-                // acceptable to do so, but I intend to support even
-                // Deathstation 9000s with this library. So... use libc types.
-                (&b"void"[..], &b"libc::c_void"[..]),
-                (&b"char"[..], &b"libc::c_char"[..]),
-                (&b"unsigned char"[..], &b"libc::c_uchar"[..]),
-                (&b"signed char"[..], &b"libc::c_schar"[..]),
-                (&b"short"[..], &b"libc::c_short"[..]),
-                (&b"unsigned short"[..], &b"libc::c_ushort"[..]),
-                (&b"int"[..], &b"libc::c_int"[..]),
-                (&b"unsigned int"[..], &b"libc::c_uint"[..]),
-                (&b"long"[..], &b"libc::c_long"[..]),
-                (&b"unsigned long"[..], &b"libc::c_ulong"[..]),
-                (&b"float"[..], &b"libc::c_float"[..]),
-                (&b"double"[..], &b"libc::c_double"[..]),
-                // C99-ish types
-                (&b"ptrdiff_t"[..], &b"libc::ptrdiff_t"[..]),
-                (&b"intptr_t"[..], &b"libc::intptr_t"[..]),
-                (&b"size_t"[..], &b"libc::size_t"[..]),
-                (&b"ssize_t"[..], &b"libc::ssize_t"[..]),
-                (&b"int8_t"[..], &b"libc::int8_t"[..]),
-                (&b"int16_t"[..], &b"libc::int16_t"[..]),
-                (&b"int32_t"[..], &b"libc::int32_t"[..]),
-                (&b"int64_t"[..], &b"libc::int64_t"[..]),
-                (&b"uint8_t"[..], &b"libc::uint8_t"[..]),
-                (&b"uint16_t"[..], &b"libc::uint16_t"[..]),
-                (&b"uint32_t"[..], &b"libc::uint32_t"[..]),
-                (&b"uint64_t"[..], &b"libc::uint64_t"[..]),
-                // "khrplatform.h" types... sigh...
-                (&b"khronos_ptrdiff_t"[..], &b"isize"[..]),
-                (&b"khronos_intptr_t"[..], &b"usize"[..]),
-                (&b"khronos_size_t"[..], &b"usize"[..]),
-                (&b"khronos_ssize_t"[..], &b"isize"[..]),
-                (&b"khronos_int8_t"[..], &b"i8"[..]),
-                (&b"khronos_int16_t"[..], &b"i16"[..]),
-                (&b"khronos_int32_t"[..], &b"i32"[..]),
-                (&b"khronos_int64_t"[..], &b"i64"[..]),
-                (&b"khronos_uint8_t"[..], &b"u8"[..]),
-                (&b"khronos_uint16_t"[..], &b"u16"[..]),
-                (&b"khronos_uint32_t"[..], &b"u32"[..]),
-                (&b"khronos_uint64_t"[..], &b"u64"[..]),
-                (&b"khronos_float_t"[..], &b"f32"[..]),
-                (&b"khronos_double_t"[..], &b"f64"[..]),
-            ].into_iter().collect();
-        static ref STATIC_TYPES_NO_LIBC: HashMap<&'static [u8], &'static [u8]>
-            = [
-                (&b"void"[..], &b"()"[..]),
-                (&b"char"[..], &b"u8"[..]),
-                (&b"unsigned char"[..], &b"u8"[..]),
-                (&b"signed char"[..], &b"i8"[..]),
-                (&b"short"[..], &b"i16"[..]),
-                (&b"unsigned short"[..], &b"u16"[..]),
-                (&b"int"[..], &b"i32"[..]),
-                (&b"unsigned int"[..], &b"u32"[..]),
-                (&b"long"[..], &b"i32"[..]),
-                (&b"unsigned long"[..], &b"u32"[..]),
-                (&b"float"[..], &b"f32"[..]),
-                (&b"double"[..], &b"f64"[..]),
-                // C99-ish types
-                (&b"ptrdiff_t"[..], &b"isize"[..]),
-                (&b"intptr_t"[..], &b"usize"[..]),
-                (&b"size_t"[..], &b"usize"[..]),
-                (&b"ssize_t"[..], &b"isize"[..]),
-                (&b"int8_t"[..], &b"i8"[..]),
-                (&b"int16_t"[..], &b"i16"[..]),
-                (&b"int32_t"[..], &b"i32"[..]),
-                (&b"int64_t"[..], &b"i64"[..]),
-                (&b"uint8_t"[..], &b"u8"[..]),
-                (&b"uint16_t"[..], &b"u16"[..]),
-                (&b"uint32_t"[..], &b"u32"[..]),
-                (&b"uint64_t"[..], &b"u64"[..]),
-                // "khrplatform.h" types... sigh...
-                (&b"khronos_ptrdiff_t"[..], &b"isize"[..]),
-                (&b"khronos_intptr_t"[..], &b"usize"[..]),
-                (&b"khronos_size_t"[..], &b"usize"[..]),
-                (&b"khronos_ssize_t"[..], &b"isize"[..]),
-                (&b"khronos_int8_t"[..], &b"i8"[..]),
-                (&b"khronos_int16_t"[..], &b"i16"[..]),
-                (&b"khronos_int32_t"[..], &b"i32"[..]),
-                (&b"khronos_int64_t"[..], &b"i64"[..]),
-                (&b"khronos_uint8_t"[..], &b"u8"[..]),
-                (&b"khronos_uint16_t"[..], &b"u16"[..]),
-                (&b"khronos_uint32_t"[..], &b"u32"[..]),
-                (&b"khronos_uint64_t"[..], &b"u64"[..]),
-                (&b"khronos_float_t"[..], &b"f32"[..]),
-                (&b"khronos_double_t"[..], &b"f64"[..]),
-            ].into_iter().collect();
+    let text = std::str::from_utf8(text).unwrap();
+    // `None` is an `#else`
+    let mut conditions: Vec<Option<String>> = Vec::new();
+    let mut bodies: Vec<String> = Vec::new();
+    let mut open = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let directive = match line.strip_prefix('#') {
+            None if open => {
+                let body = bodies.last_mut().unwrap();
+                body.push_str(line);
+                body.push('\n');
+                continue;
+            }
+            None => panic!(
+                "{}'s definition has text outside its #if/#endif",
+                name
+            ),
+            Some(directive) => directive.trim_start(),
+        };
+        let (keyword, rest) = directive
+            .split_once(char::is_whitespace)
+            .unwrap_or((directive, ""));
+        let rest = rest.trim();
+        let condition = match keyword {
+            "if" if !open && conditions.is_empty() => {
+                Some(preprocessor_condition_to_cfg(name, rest))
+            }
+            "ifdef" if !open && conditions.is_empty() => {
+                Some(platform_macro_to_cfg(name, rest).to_owned())
+            }
+            "ifndef" if !open && conditions.is_empty() => {
+                Some(format!("not({})", platform_macro_to_cfg(name, rest)))
+            }
+            "elif" if open && conditions.last().unwrap().is_some() => {
+                Some(preprocessor_condition_to_cfg(name, rest))
+            }
+            "else" if open && conditions.last().unwrap().is_some() => None,
+            "endif" if open => {
+                open = false;
+                continue;
+            }
+            _ => panic!(
+                "{}'s definition has an unsupported or misplaced \
+                 preprocessor directive: {}",
+                name, line
+            ),
+        };
+        conditions.push(condition);
+        bodies.push(String::new());
+        open = true;
+    }
+    if open || conditions.is_empty() {
+        panic!("{}'s definition has an unterminated #if", name);
     }
-    let static_types = if opts.use_libc {
-        &*STATIC_TYPES_LIBC
-    } else {
-        &*STATIC_TYPES_NO_LIBC
-    };
-    let temp =
-        CONDENSE_SPACES_1.replace_all(c_type, |_caps: &Captures| Vec::new());
-    let temp = CONDENSE_SPACES_2
-        .replace_all(&temp, |caps: &Captures| caps[1].to_vec());
-    let temp =
-        STRUCT_MUNCHER.replace_all(&temp, |_caps: &Captures| b"void".to_vec());
-    let caps = POINTER_MUNCHER.captures(&temp).unwrap();
-    let point = if !caps[1].is_empty() {
-        &b"*const"[..]
-    } else {
-        &b"*mut"[..]
-    };
-    let num_pointers = caps[3].len();
     let mut ret = Vec::new();
-    for _ in 0..num_pointers {
-        ret.write_all(point).unwrap();
+    if let Some(comment) = conditional_type_comment(name) {
+        ret.write_all(comment.as_bytes()).unwrap();
     }
-    let old_type = &caps[2];
-    if let Some(result) = static_types.get(old_type) {
-        if *result != b"()" && num_pointers > 0 {
-            ret.push(b' ');
+    for (i, (condition, body)) in conditions.iter().zip(bodies).enumerate() {
+        // each branch only applies if none of the branches before it did
+        let earlier: Vec<String> =
+            conditions[..i].iter().flatten().cloned().collect();
+        let mut terms = Vec::new();
+        if !earlier.is_empty() {
+            terms.push(format!("not({})", combine_cfg("any", earlier)));
         }
-        ret.write_all(result).unwrap();
-    } else {
-        let old_type_as_string = String::from_utf8(old_type.to_vec()).unwrap();
-        if map.contains_key(&old_type_as_string) {
-            if !requires.contains(&old_type_as_string) {
-                requires.push(old_type_as_string);
-            }
-            if old_type != b"()" && num_pointers > 0 {
-                ret.push(b' ');
+        if let Some(condition) = condition {
+            terms.push(condition.clone());
+        }
+        if let Some(code) = translate_type(
+            name,
+            body.trim().as_bytes(),
+            map,
+            order,
+            requires,
+            opts,
+        ) {
+            if !ret.is_empty() && !ret.ends_with(b"\n") {
+                ret.push(b'\n');
             }
-            ret.write_all(old_type).unwrap();
-        } else {
-            panic!("Can't find the Rust equivalent to: {}", unsafe {
-                String::from_utf8_unchecked(old_type.to_vec())
-            });
+            writeln!(ret, "#[cfg({})]", combine_cfg("all", terms)).unwrap();
+            ret.write_all(&code).unwrap();
         }
     }
-    ret.to_vec()
+    ret
+}
+
+/// Translates the C definition of a single type into Rust. Returns `None` for
+/// the "types" that are really just preprocessor boilerplate.
+fn translate_type(
+    name: &str,
+    text: &[u8],
+    map: &mut HashMap<String, Type>,
+    order: &mut Vec<String>,
+    requires: &mut Vec<String>,
+    opts: &CmdLine,
+) -> Option<Vec<u8>> {
+    if name == "stddef" || name == "khrplatform" || name == "inttypes" {
+        // These are "dependencies GL types require to be declared legally".
+        // They mainly consist of preprocessor directives (some of them in
+        // `#if` blocks of their own). We don't make any use of them.
+        return None;
+    } else if text.starts_with(b"#if") {
+        return Some(translate_conditional_type(
+            name, text, map, order, requires, opts,
+        ));
+    }
+    let text = String::from_utf8_lossy(text);
+    let (is_typedef, text) = match text.trim().strip_prefix("typedef ") {
+        Some(rest) => (true, rest),
+        None => (false, text.trim()),
+    };
+    let declaration = match parse_declaration(text) {
+        Ok(declaration) => declaration,
+        Err(why) => type_parse_failure(name, &why),
+    };
+    if !is_typedef {
+        // `struct _cl_event;`, which is only ever pointed to
+        return match declaration {
+            Declaration {
+                name: None,
+                ty: CType::Named { name: ref tag, .. },
+            } if tag == name && tag.starts_with("struct ") => {
+                Some(opaque_struct(name).into())
+            }
+            _ => type_parse_failure(name, "it isn't a typedef"),
+        };
+    }
+    if declaration.name.as_deref() != Some(name) {
+        panic!(
+            "{}'s name isn't its name!? ({})",
+            name,
+            declaration.name.as_deref().unwrap_or("")
+        );
+    }
+    let rust = declaration
+        .ty
+        .to_rust(opts, &mut |x| resolve_type(map, order, x, requires));
+    Some(format!("pub type {} = {};", rust_identifier(name), rust).into())
 }
 
 fn gather_type(
@@ -226,131 +376,7 @@ fn gather_type(
         }),
         Some(name) => name,
     };
-    let code;
-    lazy_static! {
-        static ref SIMPLE_TYPEDEF: Regex
-            = Regex::new(r#"^typedef (.* \**)([_a-zA-Z][_a-zA-Z0-9]*);$"#)
-            .unwrap();
-        static ref OPAQUE_STRUCT: Regex
-            = Regex::new(r#"^(struct [_a-zA-Z][_a-zA-Z0-9]*);$"#).unwrap();
-        static ref FUNCTION_POINTER: Regex
-            = Regex::new(r#"^typedef (.*) *\( *\* *([_a-zA-Z][_a-zA-Z0-9]*) *\) *\((.*)\);$"#).unwrap();
-        static ref NAME_AND_TYPE_EXTRACTOR: Regex
-            = Regex::new(r#"^ *(.* \**)([_a-zA-Z][_a-zA-Z0-9]*)$"#)
-            .unwrap();
-    }
-    let text = text.as_slice();
-    if let Some(result) = SIMPLE_TYPEDEF.captures(text) {
-        if result[2] != *name.as_bytes() {
-            panic!("{}'s name isn't its name!? ({})", name, unsafe {
-                String::from_utf8_unchecked(result[2].to_vec())
-            });
-        }
-        let new_type: Vec<u8> =
-            result[2].iter().map(space_to_underscore).collect();
-        let underlying_type =
-            c_type_to_rust_type(map, &result[1], &mut requires, opts);
-        let mut vec = Vec::new();
-        vec.write_all(b"pub type ").unwrap();
-        vec.write_all(new_type.as_slice()).unwrap();
-        vec.write_all(b" = ").unwrap();
-        vec.write_all(&underlying_type).unwrap();
-        vec.write_all(b";").unwrap();
-        code = Some(vec);
-    } else if let Some(result) = OPAQUE_STRUCT.captures(text) {
-        if result[1] != *name.as_bytes() {
-            panic!("{}'s name isn't its name!? ({})", name, unsafe {
-                String::from_utf8_unchecked(result[1].to_vec())
-            });
-        }
-        let new_type: Vec<u8> =
-            result[1].iter().map(space_to_underscore).collect();
-        let mut vec = Vec::new();
-        vec.write_all(b"type ").unwrap();
-        vec.write_all(new_type.as_slice()).unwrap();
-        vec.write_all(b" = ();").unwrap();
-        code = Some(vec);
-    } else if let Some(result) = FUNCTION_POINTER.captures(text) {
-        if result[2] != *name.as_bytes() {
-            panic!("{}'s name isn't its name!? ({})", name, unsafe {
-                String::from_utf8_unchecked(result[2].to_vec())
-            });
-        }
-        let new_type: Vec<u8> =
-            result[2].iter().map(space_to_underscore).collect();
-        let return_type =
-            c_type_to_rust_type(map, &result[1], &mut requires, opts);
-        let mut vec = Vec::new();
-        vec.write_all(b"pub type ").unwrap();
-        vec.write_all(new_type.as_slice()).unwrap();
-        vec.write_all(b" = Option<extern \"C\" fn(").unwrap();
-        let mut first_param = true;
-        if result[3] != b"void"[..] {
-            for param in result[3].split(|x| *x == b',') {
-                if first_param {
-                    first_param = false;
-                } else {
-                    vec.write_all(b", ").unwrap();
-                }
-                if let Some(caps) = NAME_AND_TYPE_EXTRACTOR.captures(param) {
-                    let param_type = c_type_to_rust_type(
-                        map,
-                        &caps[1],
-                        &mut requires,
-                        opts,
-                    );
-                    let param_name: &[u8] = match &caps[2] {
-                        b"type" => b"r#type",
-                        b"ref" => b"r#ref",
-                        x => x,
-                    };
-                    vec.write_all(param_name).unwrap();
-                    vec.write_all(b": ").unwrap();
-                    vec.write_all(&param_type[..]).unwrap();
-                } else {
-                    let param_type =
-                        c_type_to_rust_type(map, param, &mut requires, opts);
-                    vec.write_all(b"_: ").unwrap();
-                    vec.write_all(&param_type[..]).unwrap();
-                }
-            }
-        }
-        vec.write_all(b")").unwrap();
-        if return_type != b"libc::c_void" {
-            vec.write_all(b" -> ").unwrap();
-            vec.write_all(&return_type).unwrap();
-        }
-        vec.write_all(b">;").unwrap();
-        code = Some(vec);
-    } else if name == "GLhandleARB" {
-        assert_eq!(
-            text,
-            &br#"#ifdef __APPLE__
-typedef void *GLhandleARB;
-#else
-typedef unsigned int GLhandleARB;
-#endif"#[..]
-        );
-        code = Some(br#"// For historical reasons, this definition differs between macOS and other
-// platforms. When the extension was promoted to core in GL 2.0, the definition
-// was tightened. It's best to use the core versions of the routines that need
-// handles rather than the extensions.
-#[cfg(target_os = "macos")]
-type GLhandleARB = *mut();
-#[cfg(target_os != "macos")]
-type GLhandleARB = libc::c_uint;"#.to_vec());
-    } else if name == "stddef" || name == "khrplatform" || name == "inttypes" {
-        // These are "dependencies GL types require to be declared legally".
-        // They mainly consist of preprocessor directives. We don't make any
-        // use of them.
-        code = None;
-    } else {
-        eprintln!("!!!!!!!!!!!!!!!!!!!!!!");
-        eprintln!("! TYPE PARSE FAILURE !");
-        eprintln!("!!!!!!!!!!!!!!!!!!!!!!");
-        eprintln!("We couldn't understand the definition for `{}`.", name);
-        panic!("Type parse failure");
-    }
+    let code = translate_type(&name, &text, map, order, &mut requires, opts);
     /*if name.starts_with("GL") {
         if let Some(ref mut code) = code {
             code.write_all(b"\npub use ").unwrap();
@@ -362,6 +388,7 @@ type GLhandleARB = libc::c_uint;"#.to_vec());
     }*/
     let mut result = Type {
         code: unsafe { code.map(|x| String::from_utf8_unchecked(x)) },
+        requires,
     };
     if let Some(comment) = tag.get_attributes().get("comment") {
         let mut new_code = Vec::new();
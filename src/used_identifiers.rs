@@ -2,20 +2,103 @@ use std::{
     collections::HashSet,
     fs::File,
     io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::exit,
 };
 
+use regex::Regex;
+
 use crate::cmdline::CmdLine;
 
+#[derive(Clone)]
 pub enum UsedIdentifiers {
     NotApplicable,
-    Applicable(HashSet<String>),
+    Applicable(HashSet<String>, Vec<Regex>),
 }
 
 impl UsedIdentifiers {
     pub fn contains(&self, key: &str) -> bool {
         match self {
             UsedIdentifiers::NotApplicable => true,
-            UsedIdentifiers::Applicable(set) => set.contains(key),
+            UsedIdentifiers::Applicable(set, patterns) => {
+                set.contains(key) || patterns.iter().any(|x| x.is_match(key))
+            }
+        }
+    }
+    /// Marks an identifier as used, as if it had been listed in the file.
+    pub fn insert(&mut self, key: &str) {
+        if let UsedIdentifiers::Applicable(set, _) = self {
+            if !set.contains(key) {
+                set.insert(key.to_owned());
+            }
+        }
+    }
+}
+
+/// Turns a glob like `glUniform*` into an anchored regex. `*` matches any
+/// run of characters, `?` matches exactly one, everything else is literal.
+fn glob_to_regex(glob: &str) -> Regex {
+    let mut pattern = String::with_capacity(glob.len() + 8);
+    pattern.push('^');
+    for ch in glob.chars() {
+        match ch {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            x => pattern.push_str(&regex::escape(&x.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).unwrap()
+}
+
+fn read_used_identifiers(
+    path: &Path,
+    visited: &mut HashSet<PathBuf>,
+    set: &mut HashSet<String>,
+    patterns: &mut Vec<Regex>,
+) {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(x) => {
+            eprintln!("{}: {}", path.display(), x);
+            exit(1);
+        }
+    };
+    // Including the same file twice (whether from a cycle or from two
+    // different lists sharing a base) is harmless, so just skip it.
+    if !visited.insert(path.canonicalize().unwrap()) {
+        return;
+    }
+    for line in BufReader::new(file).lines() {
+        let line = line.unwrap();
+        // (trimming also takes care of DOS line endings)
+        let line = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None => &line[..],
+        }
+        .trim();
+        // (the directive has to be a word of its own, so that
+        // `@includefoo.txt` isn't taken for `@include foo.txt`)
+        let (word, rest) =
+            line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if line.is_empty() {
+            continue;
+        } else if word == "@include" {
+            let include = rest.trim();
+            if include.is_empty() {
+                eprintln!("{}: @include without a path", path.display());
+                exit(1);
+            }
+            // relative paths are relative to the including file
+            let include = path
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(include);
+            read_used_identifiers(&include, visited, set, patterns);
+        } else if line.contains(['*', '?']) {
+            patterns.push(glob_to_regex(line));
+        } else {
+            set.insert(line.to_owned());
         }
     }
 }
@@ -24,18 +107,11 @@ pub fn get_used_identifiers(opts: &CmdLine) -> UsedIdentifiers {
     match opts.used_identifiers_path {
         None => UsedIdentifiers::NotApplicable,
         Some(ref path) => {
-            let file = BufReader::new(File::open(path).unwrap());
+            let mut visited = HashSet::new();
             let mut set = HashSet::new();
-            for line in file.lines() {
-                let mut line = line.unwrap();
-                // thanks, DOS!
-                if line.ends_with('\r') {
-                    let llen = line.len();
-                    line.truncate(llen - 1);
-                }
-                set.insert(line);
-            }
-            UsedIdentifiers::Applicable(set)
+            let mut patterns = Vec::new();
+            read_used_identifiers(path, &mut visited, &mut set, &mut patterns);
+            UsedIdentifiers::Applicable(set, patterns)
         }
     }
 }
@@ -1,10 +1,18 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cmdline::CmdLine,
     dom::{Element, Node},
+    groups::Group,
+    naming::constant_name,
 };
 
+#[derive(PartialEq, Serialize, Deserialize)]
 pub enum Val {
     U32(u32),
     I32(i32),
@@ -12,11 +20,18 @@ pub enum Val {
 }
 
 impl Val {
-    pub fn output(&self, name: &str, _opts: &CmdLine) {
+    pub fn output<W: Write>(&self, name: &str, opts: &CmdLine, out: &mut W) {
+        let name = constant_name(name, opts);
         match *self {
-            Val::U32(x) => println!("pub const {}: u32 = 0x{:x};", name, x),
-            Val::I32(x) => println!("pub const {}: i32 = {};", name, x),
-            Val::U64(x) => println!("pub const {}: u64 = 0x{:x};", name, x),
+            Val::U32(x) => {
+                writeln!(out, "pub const {}: u32 = 0x{:x};", name, x).unwrap()
+            }
+            Val::I32(x) => {
+                writeln!(out, "pub const {}: i32 = {};", name, x).unwrap()
+            }
+            Val::U64(x) => {
+                writeln!(out, "pub const {}: u64 = 0x{:x};", name, x).unwrap()
+            }
         }
     }
 }
@@ -48,33 +63,52 @@ fn parse_value(str: &str, typ: Option<&str>) -> Val {
 
 pub fn gather_values(
     root: &Element,
-    _opts: &CmdLine,
+    opts: &CmdLine,
 ) -> (HashMap<String, Val>, Vec<String>) {
     let mut map = HashMap::new();
+    // Some enums have different values in different APIs. Those definitions
+    // carry an `api` attribute, and take precedence over any that don't.
+    let mut api_specific = HashSet::new();
     for child in root.get_children() {
         if let Node::Element(ref element) = child {
             if element.get_name() == "enums" {
                 for child in element.get_children() {
                     if let Node::Element(ref element) = child {
-                        if element.get_name() == "enum" {
-                            if element.get_attributes().contains_key("alias") {
-                                // ignore!
-                            } else if let Some(enum_name) =
-                                element.get_attributes().get("name")
-                            {
-                                assert!(!map.contains_key(enum_name.as_str()));
-                                map.insert(
-                                    (*enum_name).clone(),
-                                    parse_value(
-                                        element.get_attributes()["value"]
-                                            .as_str(),
-                                        element
-                                            .get_attributes()
-                                            .get("type")
-                                            .map(|x| x.as_str()),
-                                    ),
-                                );
+                        if element.get_name() != "enum"
+                            || !opts.version.correct_api(element)
+                        {
+                            continue;
+                        }
+                        let attributes = element.get_attributes();
+                        if attributes.contains_key("alias") {
+                            // ignore!
+                        } else if let Some(enum_name) = attributes.get("name")
+                        {
+                            let specific = attributes.contains_key("api");
+                            if map.contains_key(enum_name.as_str()) {
+                                if !specific
+                                    && api_specific.contains(enum_name)
+                                {
+                                    continue;
+                                }
+                                if specific == api_specific.contains(enum_name)
+                                {
+                                    panic!(
+                                        "{} is defined more than once",
+                                        enum_name
+                                    );
+                                }
+                            }
+                            if specific {
+                                api_specific.insert(enum_name.clone());
                             }
+                            map.insert(
+                                (*enum_name).clone(),
+                                parse_value(
+                                    attributes["value"].as_str(),
+                                    attributes.get("type").map(|x| x.as_str()),
+                                ),
+                            );
                         }
                     }
                 }
@@ -88,3 +122,89 @@ pub fn gather_values(
     order.sort();
     (map, order)
 }
+
+/// Outputs `enum_name` and `enum_name_in`, which turn the value of any
+/// emitted `u32` constant back into its name. `emitted` lists the constants,
+/// and the extensions that provide them (`""` for core).
+pub fn output_enum_names<W: Write>(
+    emitted: &[(&str, &str)],
+    value_map: &HashMap<String, Val>,
+    group_map: &HashMap<String, Group>,
+    out: &mut W,
+) {
+    let mut names: Vec<(u32, bool, &str)> = emitted
+        .iter()
+        .filter_map(|&(name, ext)| match value_map[name] {
+            Val::U32(x) => Some((x, !ext.is_empty(), name)),
+            _ => None,
+        })
+        .collect();
+    if names.is_empty() {
+        return;
+    }
+    // When several constants share a value, core ones beat extension ones,
+    // and then they go in alphabetical order.
+    names.sort_unstable();
+    let emitted: HashMap<&str, (u32, bool)> = names
+        .iter()
+        .map(|&(value, extension, name)| (name, (value, extension)))
+        .collect();
+    let mut group_names: Vec<&String> = group_map.keys().collect();
+    group_names.sort_unstable();
+    let mut grouped: Vec<(&str, u32, bool, &str)> = Vec::new();
+    for group in group_names {
+        for name in group_map[group].get_elements() {
+            if let Some(&(value, extension)) = emitted.get(name.as_str()) {
+                grouped.push((group, value, extension, name));
+            }
+        }
+    }
+    grouped.sort_unstable();
+    names.dedup_by_key(|x| x.0);
+    grouped.dedup_by_key(|x| (x.0, x.1));
+    writeln!(
+        out,
+        "\nstatic ENUM_NAMES: [(u32, &str); {}] = [",
+        names.len()
+    )
+    .unwrap();
+    for (value, _, name) in &names {
+        writeln!(out, "    (0x{:x}, \"{}\"),", value, name).unwrap();
+    }
+    writeln!(
+        out,
+        "];\nstatic GROUPED_ENUM_NAMES: [(&str, u32, &str); {}] = [",
+        grouped.len()
+    )
+    .unwrap();
+    for (group, value, _, name) in &grouped {
+        writeln!(out, "    (\"{}\", 0x{:x}, \"{}\"),", group, value, name)
+            .unwrap();
+    }
+    write!(
+        out,
+        r#"];
+
+/// Returns the name of a constant with the given value, like
+/// `"GL_INVALID_ENUM"` for `0x0500`. Many values have several names; if you
+/// know what group the value belongs to, `enum_name_in` can pick the right
+/// one.
+pub fn enum_name(value: u32) -> Option<&'static str> {{
+    ENUM_NAMES
+        .binary_search_by_key(&value, |x| x.0)
+        .ok()
+        .map(|i| ENUM_NAMES[i].1)
+}}
+
+/// Returns the name of a constant with the given value in the given group
+/// (as named in `gl.xml`, like `"ErrorCode"` or `"PrimitiveType"`).
+pub fn enum_name_in(group: &str, value: u32) -> Option<&'static str> {{
+    GROUPED_ENUM_NAMES
+        .binary_search_by(|x| (x.0, x.1).cmp(&(group, value)))
+        .ok()
+        .map(|i| GROUPED_ENUM_NAMES[i].2)
+}}
+"#
+    )
+    .unwrap();
+}
@@ -5,7 +5,7 @@ use regex::Regex;
 
 use crate::dom::Element;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ActiveVersion {
     api: String,             // gl, gles1, gles2
     profile: String,         // core/compatibility (gl), blank (gles)
@@ -56,6 +56,19 @@ impl ActiveVersion {
             _ => false,
         }
     }
+    /// A name for the module containing a binding for this version, when
+    /// generating several at once (e.g. `gl33`, `glcore41`, `gles20`).
+    pub fn module_name(&self) -> String {
+        let prefix = if self.api.starts_with("gles") {
+            "gles"
+        } else {
+            &self.extension_space
+        };
+        format!("{}{}", prefix, self.number.replace('.', ""))
+    }
+    pub fn is_compatibility(&self) -> bool {
+        self.profile == "compatibility"
+    }
     pub fn needs_getstringi_extensions(&self) -> bool {
         !(self.number.starts_with("1.") || self.number.starts_with("2."))
     }